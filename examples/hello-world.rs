@@ -14,7 +14,7 @@ fn main() -> Result<()> {
     let mut payload = PayloadBuffer::new();
 
     payload.add_message_drawable(
-        Style::default().mode(Mode::Center),
+        Style::default().with_mode(Mode::Center),
         &Text::new(
             "Hello",
             Point::new(0, 8),
@@ -26,7 +26,7 @@ fn main() -> Result<()> {
     );
 
     payload.add_message_drawable(
-        Style::default().mode(Mode::Center),
+        Style::default().with_mode(Mode::Center),
         &Text::new(
             "Hello",
             Point::new(0, 5),
@@ -0,0 +1,321 @@
+//! Badges saved by the Badge Magic apps for Android and iOS
+//!
+//! The FOSSASIA Badge Magic apps save badges as JSON. Every message stores its pixels as
+//! a list of hex strings, one string of 22 hex digits per group of 8 columns
+//! (one byte per row, the most significant bit is the left column).
+//! Speed and mode are stored as hex strings of the byte sent to the badge.
+//! ```
+//! use badgemagic::{
+//!     badge_magic::SavedBadge,
+//!     protocol::{Mode, Speed, State},
+//! };
+//!
+//! let badge: SavedBadge = serde_json::from_str(
+//!     r#"{
+//!         "messages": [{
+//!             "text": ["00386CC6C6FEC6C6C6C600", "00FC6666667C6666FC0000"],
+//!             "flash": false,
+//!             "marquee": true,
+//!             "speed": "0x70",
+//!             "mode": "0x04"
+//!         }]
+//!     }"#,
+//! )
+//! .unwrap();
+//!
+//! let payload = badge.to_payload().unwrap();
+//! let message = payload.messages().next().unwrap();
+//! assert_eq!(message.width(), 16);
+//! assert_eq!(message.style().speed(), Speed::Fps15);
+//! assert_eq!(message.style().mode(), Mode::Center);
+//! assert_eq!(message.get((2, 1)), Some(State::On));
+//! ```
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::protocol::{Bitmap, Mode, PayloadBuffer, Speed, State, Style};
+
+/// A badge with all its messages, as saved by the apps
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedBadge {
+    pub messages: Vec<SavedMessage>,
+}
+
+/// A single message of a [`SavedBadge`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedMessage {
+    /// Pixels of the message, the width is a multiple of 8 columns
+    #[serde(
+        serialize_with = "serialize_text",
+        deserialize_with = "deserialize_text"
+    )]
+    pub text: Bitmap,
+
+    /// Let the message blink
+    #[serde(default)]
+    pub flash: bool,
+
+    /// Show an animated border around the display
+    #[serde(default)]
+    pub marquee: bool,
+
+    #[serde(
+        default,
+        serialize_with = "serialize_speed",
+        deserialize_with = "deserialize_speed"
+    )]
+    pub speed: Speed,
+
+    #[serde(
+        default,
+        serialize_with = "serialize_mode",
+        deserialize_with = "deserialize_mode"
+    )]
+    pub mode: Mode,
+}
+
+impl SavedBadge {
+    /// Convert all messages of `payload`
+    ///
+    /// The apps don't support inverted messages, those are saved with the pixels shown on the display.
+    /// The brightness is not saved.
+    #[must_use]
+    pub fn from_payload(payload: &PayloadBuffer) -> Self {
+        let messages = payload
+            .messages()
+            .map(|message| {
+                let style = message.style();
+                let mut text = message.to_bitmap();
+                if style.is_invert() {
+                    text.as_message_buffer().invert();
+                }
+                SavedMessage {
+                    text,
+                    flash: style.is_blink(),
+                    marquee: style.is_border(),
+                    speed: style.speed(),
+                    mode: style.mode(),
+                }
+            })
+            .collect();
+        Self { messages }
+    }
+
+    /// Build a payload with all messages
+    pub fn to_payload(&self) -> Result<PayloadBuffer> {
+        anyhow::ensure!(
+            self.messages.len() <= 8,
+            "the badge supports at most 8 messages, found {} messages",
+            self.messages.len()
+        );
+        let mut payload = PayloadBuffer::new();
+        for message in &self.messages {
+            payload.add_message_bitmap(message.style(), &message.text);
+        }
+        Ok(payload)
+    }
+
+    /// Convert the badge into a config, the messages are stored as bitstrings
+    #[cfg(feature = "embedded-graphics")]
+    #[must_use]
+    pub fn to_config(&self) -> crate::config::BadgeConfig {
+        use crate::config::{BadgeConfig, Content, MessageSpec};
+
+        let messages = self
+            .messages
+            .iter()
+            .map(|message| {
+                let bitstring = (0..11)
+                    .map(|y| {
+                        (0..message.text.width())
+                            .map(|x| match message.text.get((x, y)) {
+                                Some(State::On) => 'X',
+                                _ => '_',
+                            })
+                            .collect::<String>()
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                MessageSpec {
+                    blink: message.flash,
+                    border: message.marquee,
+                    negative: false,
+                    speed: Some(message.speed),
+                    mode: message.mode,
+                    content: Content::Bitstring { bitstring },
+                }
+            })
+            .collect();
+        BadgeConfig {
+            brightness: crate::protocol::Brightness::default(),
+            messages,
+        }
+    }
+}
+
+impl SavedMessage {
+    /// Return the style of the message
+    pub fn style(&self) -> Style {
+        let mut style = Style::default().with_speed(self.speed).with_mode(self.mode);
+        if self.flash {
+            style = style.blink();
+        }
+        if self.marquee {
+            style = style.border();
+        }
+        style
+    }
+}
+
+/// Parse a byte written as hex digits, with an optional `0x` prefix
+fn parse_byte(text: &str) -> Result<u8> {
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+    u8::from_str_radix(digits, 16).with_context(|| format!("invalid hex byte: {text:?}"))
+}
+
+fn serialize_text<S: Serializer>(bitmap: &Bitmap, serializer: S) -> Result<S::Ok, S::Error> {
+    let hex = bitmap.to_hex();
+    let groups: Vec<_> = hex
+        .as_bytes()
+        .chunks(22)
+        .map(String::from_utf8_lossy)
+        .collect();
+    groups.serialize(serializer)
+}
+
+fn deserialize_text<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Bitmap, D::Error> {
+    let groups = Vec::<String>::deserialize(deserializer)?;
+    if let Some(hex) = groups.iter().find(|hex| hex.len() != 22) {
+        return Err(serde::de::Error::custom(format!(
+            "expected 22 hex digits per column group, found {} in {hex:?}",
+            hex.len()
+        )));
+    }
+    Bitmap::from_hex(&groups.concat()).map_err(serde::de::Error::custom)
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)] // signature required by serde
+fn serialize_speed<S: Serializer>(speed: &Speed, serializer: S) -> Result<S::Ok, S::Error> {
+    format!("0x{:02x}", u8::from(*speed) << 4).serialize(serializer)
+}
+
+fn deserialize_speed<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Speed, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    parse_byte(&text)
+        .ok()
+        .filter(|byte| byte.trailing_zeros() >= 4)
+        .and_then(|byte| Speed::try_from(byte >> 4).ok())
+        .ok_or_else(|| {
+            serde::de::Error::custom(format!("invalid speed {text:?}, expected 0x00 to 0x70"))
+        })
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)] // signature required by serde
+fn serialize_mode<S: Serializer>(mode: &Mode, serializer: S) -> Result<S::Ok, S::Error> {
+    format!("0x{:02x}", u8::from(*mode)).serialize(serializer)
+}
+
+fn deserialize_mode<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Mode, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    parse_byte(&text)
+        .ok()
+        .and_then(|byte| Mode::try_from(byte).ok())
+        .ok_or_else(|| {
+            serde::de::Error::custom(format!("invalid mode {text:?}, expected 0x00 to 0x08"))
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::protocol::{Mode, PayloadBuffer, Speed, State, Style};
+
+    use super::SavedBadge;
+
+    #[test]
+    fn round_trip_payload() {
+        let mut payload = PayloadBuffer::new();
+        let mut message = payload.add_message(Style::default().blink().with_speed(Speed::Fps2), 2);
+        message.set((0, 0), State::On);
+        message.set((9, 10), State::On);
+        payload.add_message(Style::default().with_mode(Mode::Laser).border().invert(), 1);
+
+        let badge = SavedBadge::from_payload(&payload);
+        let json = serde_json::to_value(&badge).unwrap();
+        assert_eq!(
+            json["messages"][0],
+            serde_json::json!({
+                "text": ["8000000000000000000000", "0000000000000000000040"],
+                "flash": true,
+                "marquee": false,
+                "speed": "0x20",
+                "mode": "0x00",
+            })
+        );
+        // inverted messages are saved with the inverted pixels
+        assert_eq!(json["messages"][1]["text"][0], "FFFFFFFFFFFFFFFFFFFFFF");
+        assert_eq!(json["messages"][1]["mode"], "0x08");
+
+        let loaded: SavedBadge = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded, badge);
+        let loaded = loaded.to_payload().unwrap();
+        let messages: Vec<_> = loaded.messages().collect();
+        assert_eq!(
+            messages[0].to_bitmap(),
+            payload.messages().next().unwrap().to_bitmap()
+        );
+        assert_eq!(
+            messages[0].style(),
+            Style::default().blink().with_speed(Speed::Fps2)
+        );
+        assert_eq!(messages[1].get((3, 3)), Some(State::On));
+        assert!(!messages[1].style().is_invert());
+    }
+
+    #[test]
+    fn reject_invalid_fields() {
+        let parse = |message: &str| {
+            serde_json::from_str::<SavedBadge>(&format!(r#"{{"messages": [{message}]}}"#))
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(parse(r#"{"text": ["00"]}"#).contains("expected 22 hex digits"));
+        assert!(parse(r#"{"text": ["00386CC6C6FEC6C6C6C6ZZ"]}"#).contains("invalid hex digit"));
+        assert!(parse(r#"{"text": [], "speed": "0x80"}"#).contains("invalid speed"));
+        assert!(parse(r#"{"text": [], "speed": "0x11"}"#).contains("invalid speed"));
+        assert!(parse(r#"{"text": [], "mode": "0x09"}"#).contains("invalid mode"));
+    }
+
+    #[test]
+    fn too_many_messages() {
+        let badge: SavedBadge = serde_json::from_str(&format!(
+            r#"{{"messages": [{}]}}"#,
+            [r#"{"text": []}"#; 9].join(", ")
+        ))
+        .unwrap();
+        assert!(badge.to_payload().is_err());
+    }
+
+    #[cfg(feature = "embedded-graphics")]
+    #[test]
+    fn convert_to_config() {
+        let badge: SavedBadge = serde_json::from_str(
+            r#"{"messages": [{"text": ["8000000000000000000001"], "speed": "0x30", "mode": "0x01", "flash": true}]}"#,
+        )
+        .unwrap();
+        let config = badge.to_config();
+        let payload = config.render().unwrap();
+        let message = payload.messages().next().unwrap();
+        assert_eq!(message.to_bitmap(), badge.messages[0].text);
+        assert_eq!(
+            message.style(),
+            Style::default()
+                .blink()
+                .with_speed(Speed::Fps2_4)
+                .with_mode(Mode::Right)
+        );
+    }
+}
@@ -0,0 +1,579 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+//! Local HTTP API to upload messages to LED badges
+//!
+//! - `GET /devices`: list the visible USB and BLE devices (`?transport=usb` or `ble`)
+//! - `POST /upload`: upload a config (`Content-Type: application/json`) or a raw payload
+//!   (`Content-Type: application/octet-stream`) to a badge,
+//!   selected with `?transport=usb` (default) or `ble` and `&device_name=...`
+//! - `POST /preview.png` and `POST /preview.gif`: render a config or raw payload as an image
+//!
+//! Requests from web pages of other origins are rejected, unless the origin is allowed with
+//! `--allow-origin`. Configs can't load files or use templates, the server would read them on
+//! behalf of the client.
+
+use std::{
+    io::{Cursor, Read},
+    net::{IpAddr, SocketAddr},
+};
+
+use anyhow::{Context, Result};
+use badgemagic::{
+    ble::Device as BleDevice,
+    config::{BadgeConfig, Content},
+    protocol::PayloadBuffer,
+    usb_hid::Device as UsbDevice,
+    util::preview,
+};
+use clap::Parser;
+use image::{codecs::gif::GifEncoder, ImageFormat};
+use serde_json::json;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// Maximum size of a request body in bytes
+const MAX_BODY_SIZE: u64 = 1024 * 1024;
+
+#[derive(Parser)]
+/// Local HTTP API to upload messages to LED badges
+#[clap(version, author)]
+struct Args {
+    /// Address to listen on
+    ///
+    /// Every client can upload to the badges, only listen on public addresses
+    /// if every client is trusted.
+    #[clap(long, default_value = "127.0.0.1:8080")]
+    listen: SocketAddr,
+
+    /// Scale of the preview images (pixels per LED)
+    #[clap(long, default_value_t = 8)]
+    scale: u32,
+
+    /// Allow requests from web pages of this origin (e.g. `http://localhost:3000`)
+    ///
+    /// Can be given multiple times. Web pages served from the listen address are always allowed.
+    #[clap(long, value_name = "ORIGIN")]
+    allow_origin: Vec<String>,
+}
+
+/// Error response with a status code
+struct HttpError {
+    status: u16,
+    error: anyhow::Error,
+}
+
+impl HttpError {
+    fn bad_request(error: anyhow::Error) -> Self {
+        Self { status: 400, error }
+    }
+
+    fn forbidden(error: anyhow::Error) -> Self {
+        Self { status: 403, error }
+    }
+
+    fn not_found() -> Self {
+        Self {
+            status: 404,
+            error: anyhow::anyhow!("not found"),
+        }
+    }
+}
+
+impl From<anyhow::Error> for HttpError {
+    fn from(error: anyhow::Error) -> Self {
+        Self { status: 500, error }
+    }
+}
+
+type HttpResult = Result<Response<Cursor<Vec<u8>>>, HttpError>;
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let server = Server::http(args.listen)
+        .map_err(|error| anyhow::anyhow!(error))
+        .with_context(|| format!("listen on {}", args.listen))?;
+    eprintln!("listening on http://{}", args.listen);
+
+    for mut request in server.incoming_requests() {
+        let response = handle(&mut request, &args);
+        eprintln!(
+            "{} {} -> {}",
+            request.method(),
+            request.url(),
+            response.status_code().0
+        );
+        if let Err(error) = request.respond(response) {
+            eprintln!("failed to send response: {error}");
+        }
+    }
+    Ok(())
+}
+
+/// Answer a request, with CORS headers for allowed origins
+fn handle(request: &mut Request, args: &Args) -> Response<Cursor<Vec<u8>>> {
+    let (response, cors_origin) = match check_origin(request, args) {
+        Ok(cors_origin) => (route(request, args), cors_origin),
+        Err(error) => (Err(error), None),
+    };
+    let response = response.unwrap_or_else(|error| {
+        json_response(&json!({ "error": format!("{:#}", error.error) }))
+            .with_status_code(error.status)
+    });
+    match cors_origin {
+        Some(origin) => response
+            .with_header(header_field("Access-Control-Allow-Origin", &origin))
+            .with_header(header_field("Vary", "Origin")),
+        None => response,
+    }
+}
+
+fn route(request: &mut Request, args: &Args) -> HttpResult {
+    let url = request.url().to_owned();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let transport = query_param(query, "transport");
+    match (request.method(), path) {
+        (Method::Options, "/devices" | "/upload" | "/preview.png" | "/preview.gif") => {
+            // CORS preflight, the origin has been checked already
+            Ok(Response::from_data(Vec::new())
+                .with_status_code(204)
+                .with_header(header_field(
+                    "Access-Control-Allow-Methods",
+                    "GET, POST, OPTIONS",
+                ))
+                .with_header(header_field("Access-Control-Allow-Headers", "Content-Type"))
+                .with_header(header_field("Access-Control-Max-Age", "600")))
+        }
+        (Method::Get, "/devices") => list_devices(transport.as_deref()),
+        (Method::Post, "/upload") => {
+            let (payload, warnings) = read_payload(request)?;
+            let device_name = query_param(query, "device_name");
+            write_payload(transport.as_deref(), device_name.as_deref(), payload)?;
+            Ok(json_response(&json!({ "warnings": warnings })))
+        }
+        (Method::Post, "/preview.png") => {
+            let (payload, _) = read_payload(request)?;
+            let mut png = Cursor::new(Vec::new());
+            preview::image(&payload, args.scale)
+                .write_to(&mut png, ImageFormat::Png)
+                .context("encode png")?;
+            Ok(image_response(png.into_inner(), "image/png"))
+        }
+        (Method::Post, "/preview.gif") => {
+            let (payload, _) = read_payload(request)?;
+            let mut gif = Vec::new();
+            {
+                let mut encoder = GifEncoder::new(&mut gif);
+                encoder
+                    .set_repeat(image::codecs::gif::Repeat::Infinite)
+                    .context("encode gif")?;
+                encoder
+                    .encode_frames(preview::animation(&payload, args.scale))
+                    .context("encode gif")?;
+            }
+            Ok(image_response(gif, "image/gif"))
+        }
+        _ => Err(HttpError::not_found()),
+    }
+}
+
+/// Return the percent decoded value of a query parameter
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| percent_decode(key) == name)
+        .map(|(_, value)| percent_decode(value))
+}
+
+/// Decode `%XX` escapes and `+` of a query component, invalid escapes are kept as they are
+fn percent_decode(text: &str) -> String {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let decoded = tail
+                    .get(..2)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                if let Some(decoded) = decoded {
+                    bytes.push(decoded);
+                    rest = &tail[2..];
+                } else {
+                    bytes.push(b'%');
+                }
+            }
+            _ => bytes.push(byte),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Reject requests sent by web pages that are not served from the listen address or
+/// from an origin passed with `--allow-origin`
+///
+/// Requests without an `Origin` header (e.g. from `curl`) are accepted. Returns the origin
+/// if it needs CORS headers.
+fn check_origin(request: &Request, args: &Args) -> Result<Option<String>, HttpError> {
+    let Some(origin) = header(request, "Origin") else {
+        return Ok(None);
+    };
+    let listen = args.listen;
+    if args
+        .allow_origin
+        .iter()
+        .any(|allowed| allowed.trim_end_matches('/').eq_ignore_ascii_case(origin))
+    {
+        return Ok(Some(origin.to_owned()));
+    }
+    let allowed = origin
+        .strip_prefix("http://")
+        .and_then(|host| host.rsplit_once(':'))
+        .is_some_and(|(host, port)| {
+            let host = host.trim_start_matches('[').trim_end_matches(']');
+            port.parse() == Ok(listen.port())
+                && (host == "localhost"
+                    || host
+                        .parse::<IpAddr>()
+                        .is_ok_and(|ip| ip.is_loopback() || ip == listen.ip()))
+        });
+    if allowed {
+        Ok(None)
+    } else {
+        Err(HttpError::forbidden(anyhow::anyhow!(
+            "requests from origin {origin:?} are not allowed"
+        )))
+    }
+}
+
+/// Return the value of a request header
+fn header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str())
+}
+
+/// Read a raw payload or render a JSON config from the request body
+fn read_payload(request: &mut Request) -> Result<(PayloadBuffer, Vec<String>), HttpError> {
+    let mime = header(request, "Content-Type")
+        .and_then(|value| value.split(';').next())
+        .map(|mime| mime.trim().to_ascii_lowercase());
+    let is_raw = match mime.as_deref() {
+        Some("application/json") => false,
+        Some("application/octet-stream") => true,
+        _ => {
+            return Err(HttpError {
+                status: 415,
+                error: anyhow::anyhow!(
+                    "expected `Content-Type: application/json` or `application/octet-stream`"
+                ),
+            })
+        }
+    };
+
+    let mut body = Vec::new();
+    request
+        .as_reader()
+        .take(MAX_BODY_SIZE + 1)
+        .read_to_end(&mut body)
+        .context("read request body")?;
+    if body.len() as u64 > MAX_BODY_SIZE {
+        return Err(HttpError::bad_request(anyhow::anyhow!(
+            "request body too large (max {MAX_BODY_SIZE} bytes)"
+        )));
+    }
+
+    if is_raw {
+        let payload = PayloadBuffer::from_bytes(&body)
+            .context("decode payload")
+            .map_err(HttpError::bad_request)?;
+        return Ok((payload, Vec::new()));
+    }
+    let config: BadgeConfig = serde_json::from_slice(&body)
+        .context("parse config")
+        .map_err(HttpError::bad_request)?;
+    if let Some(path) = config.files().first() {
+        return Err(HttpError::forbidden(anyhow::anyhow!(
+            "files can't be loaded by the server, found `{}`",
+            path.display()
+        )));
+    }
+    if config
+        .messages
+        .iter()
+        .any(|message| matches!(message.content, Content::Text { template: true, .. }))
+    {
+        return Err(HttpError::forbidden(anyhow::anyhow!(
+            "templates are not supported by the server"
+        )));
+    }
+    let (payload, warnings) = config
+        .render_with_warnings()
+        .map_err(HttpError::bad_request)?;
+    Ok((payload, warnings.iter().map(ToString::to_string).collect()))
+}
+
+fn list_devices(transport: Option<&str>) -> HttpResult {
+    let mut devices = serde_json::Map::new();
+    if matches!(transport, None | Some("usb")) {
+        devices.insert("usb".into(), UsbDevice::list_all()?.into());
+    }
+    if matches!(transport, None | Some("ble")) {
+        let ble = runtime()?.block_on(BleDevice::list_all())?;
+        devices.insert("ble".into(), ble.into());
+    }
+    if devices.is_empty() {
+        return Err(unknown_transport());
+    }
+    Ok(json_response(&devices.into()))
+}
+
+fn write_payload(
+    transport: Option<&str>,
+    device_name: Option<&str>,
+    payload: PayloadBuffer,
+) -> Result<(), HttpError> {
+    match transport {
+        None | Some("usb") => UsbDevice::single()?.write(payload)?,
+        Some("ble") => runtime()?
+            .block_on(async { BleDevice::single(device_name).await?.write(payload).await })?,
+        Some(_) => return Err(unknown_transport()),
+    }
+    Ok(())
+}
+
+fn unknown_transport() -> HttpError {
+    HttpError::bad_request(anyhow::anyhow!(
+        "unknown transport, expected `usb` or `ble`"
+    ))
+}
+
+fn runtime() -> Result<tokio::runtime::Runtime> {
+    Ok(tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?)
+}
+
+fn json_response(value: &serde_json::Value) -> Response<Cursor<Vec<u8>>> {
+    Response::from_string(value.to_string()).with_header(content_type("application/json"))
+}
+
+fn image_response(data: Vec<u8>, mime: &str) -> Response<Cursor<Vec<u8>>> {
+    Response::from_data(data).with_header(content_type(mime))
+}
+
+fn content_type(mime: &str) -> Header {
+    header_field("Content-Type", mime)
+}
+
+fn header_field(name: &str, value: &str) -> Header {
+    Header::from_bytes(name, value).unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use tiny_http::TestRequest;
+
+    use super::*;
+
+    fn args() -> Args {
+        Args::parse_from([
+            "badgemagic-server",
+            "--allow-origin",
+            "http://localhost:3000",
+        ])
+    }
+
+    fn request(
+        method: Method,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: &'static str,
+    ) -> Request {
+        headers
+            .iter()
+            .fold(
+                TestRequest::new()
+                    .with_method(method)
+                    .with_path(path)
+                    .with_body(body),
+                |request, (name, value)| request.with_header(header_field(name, value)),
+            )
+            .into()
+    }
+
+    fn send(request: impl Into<Request>) -> (u16, Vec<(String, String)>, Vec<u8>) {
+        let response = handle(&mut request.into(), &args());
+        let headers = response
+            .headers()
+            .iter()
+            .map(|header| (header.field.to_string(), header.value.to_string()))
+            .collect();
+        let status = response.status_code().0;
+        (status, headers, response.into_reader().into_inner())
+    }
+
+    fn header_value<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+        headers
+            .iter()
+            .find(|(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn preview(body: &'static str) -> (u16, Vec<u8>) {
+        let (status, _, data) = send(request(
+            Method::Post,
+            "/preview.png",
+            &[("Content-Type", "application/json")],
+            body,
+        ));
+        (status, data)
+    }
+
+    const CONFIG: &str = r#"{"message": [{"text": "Hi"}]}"#;
+
+    #[test]
+    fn origins() {
+        let origin = |origin: &str| {
+            let headers: &[(&str, &str)] = if origin.is_empty() {
+                &[]
+            } else {
+                &[("Origin", origin)]
+            };
+            let (status, headers, _) = send(request(Method::Options, "/upload", headers, ""));
+            let allowed = header_value(&headers, "Access-Control-Allow-Origin").map(str::to_owned);
+            (status, allowed)
+        };
+
+        // no origin (e.g. curl) and pages served by the server itself don't need CORS
+        assert_eq!(origin(""), (204, None));
+        assert_eq!(origin("http://localhost:8080"), (204, None));
+        assert_eq!(origin("http://127.0.0.1:8080"), (204, None));
+        assert_eq!(
+            origin("http://localhost:3000"),
+            (204, Some("http://localhost:3000".into()))
+        );
+        assert_eq!(origin("http://localhost:3001"), (403, None));
+        assert_eq!(origin("https://localhost:8080"), (403, None));
+        assert_eq!(origin("http://example.com"), (403, None));
+
+        let (status, headers, _) = send(request(
+            Method::Options,
+            "/upload",
+            &[("Origin", "http://localhost:3000")],
+            "",
+        ));
+        assert_eq!(status, 204);
+        assert_eq!(
+            header_value(&headers, "Access-Control-Allow-Methods"),
+            Some("GET, POST, OPTIONS")
+        );
+        assert_eq!(
+            header_value(&headers, "Access-Control-Allow-Headers"),
+            Some("Content-Type")
+        );
+
+        // errors are readable by the allowed origin too
+        let (status, headers, _) = send(request(
+            Method::Post,
+            "/preview.png",
+            &[("Origin", "http://localhost:3000")],
+            CONFIG,
+        ));
+        assert_eq!(status, 415);
+        assert_eq!(
+            header_value(&headers, "Access-Control-Allow-Origin"),
+            Some("http://localhost:3000")
+        );
+
+        let (status, _, _) = send(request(
+            Method::Post,
+            "/preview.png",
+            &[
+                ("Origin", "http://example.com"),
+                ("Content-Type", "application/json"),
+            ],
+            CONFIG,
+        ));
+        assert_eq!(status, 403);
+    }
+
+    #[test]
+    fn content_type_and_size() {
+        for content_type in [
+            None,
+            Some("text/plain"),
+            Some("application/x-www-form-urlencoded"),
+        ] {
+            let headers: Vec<_> = content_type
+                .map(|value| ("Content-Type", value))
+                .into_iter()
+                .collect();
+            let (status, _, _) = send(request(Method::Post, "/preview.png", &headers, CONFIG));
+            assert_eq!(status, 415, "{content_type:?}");
+        }
+
+        let (status, _, _) = send(request(
+            Method::Post,
+            "/preview.png",
+            &[("Content-Type", "application/json; charset=utf-8")],
+            CONFIG,
+        ));
+        assert_eq!(status, 200);
+
+        let padding = " ".repeat(usize::try_from(MAX_BODY_SIZE).unwrap());
+        let (status, _) = preview(format!("{padding}{CONFIG}").leak());
+        assert_eq!(status, 400);
+    }
+
+    #[test]
+    fn forbidden_configs() {
+        for config in [
+            r#"{"message": [{"img_file": "/etc/passwd"}]}"#,
+            r#"{"message": [{"bitmap_file": "/etc/passwd", "width": 8}]}"#,
+            r#"{"message": [{"frame_files": "/etc/*"}]}"#,
+            r#"{"message": [{"text": "{file:/etc/passwd}", "template": true}]}"#,
+            r#"{"message": [{"text": "{time}", "template": true}]}"#,
+        ] {
+            assert_eq!(preview(config).0, 403, "{config}");
+        }
+        assert_eq!(preview(r#"{"message": [{"text": 1}]}"#).0, 400);
+    }
+
+    #[test]
+    fn previews() {
+        let (status, png) = preview(CONFIG);
+        assert_eq!(status, 200);
+        let image = image::load_from_memory_with_format(&png, ImageFormat::Png).unwrap();
+        assert_eq!(image.height(), 11 * 8);
+
+        let (status, headers, gif) = send(request(
+            Method::Post,
+            "/preview.gif",
+            &[("Content-Type", "application/json")],
+            CONFIG,
+        ));
+        assert_eq!(status, 200);
+        assert_eq!(header_value(&headers, "Content-Type"), Some("image/gif"));
+        assert!(gif.starts_with(b"GIF89a"));
+
+        let (status, _, _) = send(request(Method::Get, "/preview.png", &[], ""));
+        assert_eq!(status, 404);
+    }
+
+    #[test]
+    fn query_params() {
+        let query = "transport=ble&device_name=My%20Badge+2&e%73c=%zz%4";
+        assert_eq!(query_param(query, "transport").as_deref(), Some("ble"));
+        assert_eq!(
+            query_param(query, "device_name").as_deref(),
+            Some("My Badge 2")
+        );
+        assert_eq!(query_param(query, "esc").as_deref(), Some("%zz%4"));
+        assert_eq!(query_param(query, "missing"), None);
+        assert_eq!(percent_decode("%C3%A4%2B"), "ä+");
+    }
+}
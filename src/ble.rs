@@ -10,7 +10,7 @@ use btleplug::{
 use tokio::time;
 use uuid::Uuid;
 
-use crate::protocol::PayloadBuffer;
+use crate::protocol::{PayloadBuffer, MAX_PAYLOAD_SIZE};
 
 /// `0000fee0-0000-1000-8000-00805f9b34fb`
 const BADGE_SERVICE_UUID: Uuid = bleuuid::uuid_from_u16(0xfee0);
@@ -173,6 +173,15 @@ impl Device {
         }
     }
 
+    /// Connect to the device and keep the connection open for multiple payloads
+    pub async fn connect(self) -> Result<Session> {
+        self.peripheral
+            .connect()
+            .await
+            .context("bluetooth device connect")?;
+        Ok(Session { device: self })
+    }
+
     async fn write_connected(&self, payload: PayloadBuffer) -> Result<()> {
         // Get characteristic
         self.peripheral
@@ -195,7 +204,10 @@ impl Device {
         );
 
         // the device will brick itself if the payload is too long (more then 8192 bytes)
-        anyhow::ensure!(data.len() <= 8192, "payload too long (max 8192 bytes)");
+        anyhow::ensure!(
+            data.len() <= MAX_PAYLOAD_SIZE,
+            "payload too long (max {MAX_PAYLOAD_SIZE} bytes)"
+        );
 
         for chunk in data.chunks(BLE_CHAR_CHUNK_SIZE) {
             self.peripheral
@@ -207,3 +219,32 @@ impl Device {
         Ok(())
     }
 }
+
+/// A connection to a badge that stays open between payloads
+///
+/// Avoids scanning for the device and connecting to it for every payload,
+/// e.g. when the content of the badge is updated regularly.
+pub struct Session {
+    device: Device,
+}
+
+impl Session {
+    /// Write a payload to the device
+    ///
+    /// The connection is established again if it was lost since the last write.
+    pub async fn write(&self, payload: PayloadBuffer) -> Result<()> {
+        let peripheral = &self.device.peripheral;
+        if !peripheral.is_connected().await.unwrap_or(false) {
+            peripheral
+                .connect()
+                .await
+                .context("bluetooth device reconnect")?;
+        }
+        self.device.write_connected(payload).await
+    }
+
+    /// Close the connection
+    pub async fn disconnect(self) -> Result<()> {
+        Ok(self.device.peripheral.disconnect().await?)
+    }
+}
@@ -0,0 +1,1553 @@
+//! Serializable badge configuration, as used by the CLI
+//!
+//! The model can be loaded from any serde format (e.g. TOML or JSON)
+//! and rendered into a [`PayloadBuffer`].
+//! ```
+//! use badgemagic::config::{BadgeConfig, Content, MessageSpec};
+//!
+//! let config = BadgeConfig {
+//!     messages: vec![MessageSpec::new(Content::Text {
+//!         text: "Hello".into(),
+//!         fit: false,
+//!         wrap: false,
+//!         template: false,
+//!     })],
+//!     ..BadgeConfig::default()
+//! };
+//! let payload = config.render().unwrap();
+//! assert_eq!(payload.num_messages(), 1);
+//! ```
+#![allow(clippy::unnecessary_debug_formatting)]
+#![allow(clippy::too_many_lines)]
+
+mod parse;
+#[cfg(feature = "template")]
+mod template;
+
+use std::{
+    fmt::{self, Display},
+    fs,
+    io::BufReader,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use embedded_graphics::{
+    geometry::Point,
+    image::{Image, ImageRawLE},
+    pixelcolor::BinaryColor,
+    text::Text,
+};
+use image::{
+    codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder},
+    AnimationDecoder, DynamicImage, Frames, ImageFormat, ImageReader,
+};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use u8g2_fonts::{fonts::u8g2_font_lucasfont_alternate_tf, U8g2TextStyle};
+
+pub use self::parse::FieldError;
+#[cfg(feature = "svg")]
+use crate::util::svg;
+use crate::{
+    protocol::{Bitmap, Brightness, Mode, PayloadBuffer, Speed, State, Style, MAX_PAYLOAD_SIZE},
+    util::{
+        animation::{self, FrameSequence, FRAME_WIDTH},
+        raster::{BinaryImage, ImageLayout, RasterOptions},
+        text::{Alignment, FittedText, TwoLines},
+    },
+};
+
+const DISPLAY_HEIGHT: usize = 11;
+
+/// Configuration of a badge with up to 8 messages
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct BadgeConfig {
+    #[serde(default)]
+    pub brightness: Brightness,
+
+    #[serde(rename = "message", deserialize_with = "parse::messages")]
+    pub messages: Vec<MessageSpec>,
+}
+
+/// Configuration of a badge, with every message parsed on its own
+///
+/// Other than [`BadgeConfig`] it can be loaded if some messages are invalid,
+/// [`validate`](Self::validate) reports them together with all other problems.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PartialConfig {
+    #[serde(default)]
+    pub brightness: Brightness,
+
+    #[serde(rename = "message", deserialize_with = "parse::partial_messages")]
+    pub messages: Vec<Result<MessageSpec, FieldError>>,
+}
+
+/// A single message of a [`BadgeConfig`]
+///
+/// The style fields and the fields of the [`Content`] are written side by side.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "schema", schemars(extend("unevaluatedProperties" = false)))]
+#[cfg_attr(feature = "schema", schemars(transform = exclusive_content))]
+pub struct MessageSpec {
+    #[serde(default)]
+    pub blink: bool,
+
+    #[serde(default)]
+    pub border: bool,
+
+    /// Show the message inverted, see [`Style::invert`]
+    ///
+    /// Not named `invert`, because that option inverts the source image of image content.
+    #[serde(default)]
+    pub negative: bool,
+
+    /// Animation speed
+    ///
+    /// Animations in [`Mode::Fast`] use the speed closest to their frame rate if this is not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed: Option<Speed>,
+
+    #[serde(default)]
+    pub mode: Mode,
+
+    #[serde(flatten)]
+    pub content: Content,
+}
+
+/// Content of a message
+///
+/// The variant is selected by the field names,
+/// e.g. a message with a `text` field is [`Content::Text`].
+/// Only one of those fields can be used per message.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields, untagged)]
+pub enum Content {
+    /// A single line of text
+    Text {
+        text: String,
+        /// Choose the largest font that fits the display without scrolling
+        #[serde(default)]
+        fit: bool,
+        /// Allow [`fit`](Content::Text::fit) to wrap the text into two lines
+        #[serde(default)]
+        wrap: bool,
+        /// Fill in placeholders like `{date}`, requires the `template` feature
+        ///
+        /// Without it the text is shown as written.
+        #[serde(default)]
+        template: bool,
+    },
+    /// One or two lines of small text
+    Lines {
+        lines: Vec<String>,
+        #[serde(default)]
+        align: Vec<Alignment>,
+    },
+    /// Pixels drawn with `X` (on) and `_` (off), one line per row
+    Bitstring { bitstring: String },
+    /// Pixels as hex string, 22 hex digits per group of 8 columns, see [`Bitmap::from_hex`]
+    Hex { hex: String },
+    /// Raw 1 bit per pixel bitmap (little endian), encoded as base64
+    BitmapBase64 { width: u32, bitmap_base64: String },
+    /// Raw 1 bit per pixel bitmap (little endian) loaded from a file
+    BitmapFile { width: u32, bitmap_file: PathBuf },
+    /// Raster image (e.g. PNG or JPEG)
+    ImageFile {
+        img_file: PathBuf,
+        #[serde(flatten)]
+        layout: ImageLayout,
+        #[serde(flatten)]
+        raster: RasterOptions,
+    },
+    /// Vector image, rendered at the display height
+    #[cfg(feature = "svg")]
+    SvgFile {
+        svg_file: PathBuf,
+        #[serde(flatten)]
+        raster: RasterOptions,
+    },
+    /// Animated GIF
+    GifFile {
+        gif_file: PathBuf,
+        #[serde(flatten)]
+        layout: ImageLayout,
+        #[serde(flatten)]
+        raster: RasterOptions,
+    },
+    /// Animated PNG
+    ApngFile {
+        apng_file: PathBuf,
+        #[serde(flatten)]
+        layout: ImageLayout,
+        #[serde(flatten)]
+        raster: RasterOptions,
+    },
+    /// Animated WebP
+    WebpFile {
+        webp_file: PathBuf,
+        #[serde(flatten)]
+        layout: ImageLayout,
+        #[serde(flatten)]
+        raster: RasterOptions,
+    },
+    /// Animation from numbered image files in a directory or matching a glob pattern
+    ///
+    /// Files without the extension of a supported image format are skipped.
+    FrameFiles {
+        frame_files: String,
+        #[serde(flatten)]
+        layout: ImageLayout,
+        #[serde(flatten)]
+        raster: RasterOptions,
+    },
+}
+
+/// Problem found while rendering, that did not prevent the message from being added
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    /// Index of the message
+    pub message: usize,
+
+    pub text: String,
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "message {}: {}", self.message, self.text)
+    }
+}
+
+/// Severity of a [`Diagnostic`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The config can not be rendered
+    Error,
+    /// The config can be rendered, but the result might not be what was intended
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+        })
+    }
+}
+
+/// Problem found by [`BadgeConfig::validate`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+
+    /// Index of the message, `None` if the problem affects the whole config
+    pub message: Option<usize>,
+
+    /// Name of the config field that causes the problem
+    pub field: Option<&'static str>,
+
+    pub text: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.message, self.field) {
+            (Some(message), Some(field)) => write!(f, "message {message}, `{field}`: ")?,
+            (Some(message), None) => write!(f, "message {message}: ")?,
+            (None, Some(field)) => write!(f, "`{field}`: ")?,
+            (None, None) => {}
+        }
+        f.write_str(&self.text)
+    }
+}
+
+/// Result of [`BadgeConfig::validate`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Validation {
+    /// Number of columns of each message, `None` if the message could not be rendered
+    pub columns: Vec<Option<usize>>,
+
+    /// Size of the padded payload in bytes, without the messages that could not be rendered
+    pub payload_size: usize,
+
+    /// All errors and warnings, in the order of the messages
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Validation {
+    /// Return `true` if no errors were found
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.severity != Severity::Error)
+    }
+}
+
+/// Return the JSON Schema of [`BadgeConfig`]
+///
+/// Can be used by editors to validate and complete configuration files.
+#[cfg(feature = "schema")]
+#[must_use]
+pub fn json_schema() -> schemars::Schema {
+    schemars::schema_for!(BadgeConfig)
+}
+
+/// Let a message match exactly one [`Content`] variant
+///
+/// With `anyOf` the fields of all matching variants count as evaluated,
+/// so a message with e.g. both `text` and `img_file` would be accepted.
+#[cfg(feature = "schema")]
+fn exclusive_content(schema: &mut schemars::Schema) {
+    if let Some(variants) = schema.remove("anyOf") {
+        schema.insert("oneOf".into(), variants);
+    }
+}
+
+impl BadgeConfig {
+    /// Render all messages into a new payload
+    ///
+    /// Warnings are discarded, use [`render_with_warnings`](Self::render_with_warnings)
+    /// to report them.
+    pub fn render(&self) -> Result<PayloadBuffer> {
+        self.render_with_warnings().map(|(payload, _)| payload)
+    }
+
+    /// Render all messages into a new payload and collect the warnings
+    ///
+    /// The current time is used in UTC, use [`render_at`](Self::render_at) for the local time.
+    pub fn render_with_warnings(&self) -> Result<(PayloadBuffer, Vec<Warning>)> {
+        self.render_at(OffsetDateTime::now_utc())
+    }
+
+    /// Render all messages as if it was `timestamp` and collect the warnings
+    ///
+    /// The timestamp is stored in the payload and used for the `{date}` placeholders in text.
+    pub fn render_at(&self, timestamp: OffsetDateTime) -> Result<(PayloadBuffer, Vec<Warning>)> {
+        anyhow::ensure!(
+            self.messages.len() <= 8,
+            "expected at most 8 messages, found {} messages",
+            self.messages.len()
+        );
+
+        let mut payload = PayloadBuffer::with_timestamp(timestamp);
+        payload.set_brightness(self.brightness);
+
+        let mut warnings = Vec::new();
+        for (index, message) in self.messages.iter().enumerate() {
+            let mut warn = |text| {
+                warnings.push(Warning {
+                    message: index,
+                    text,
+                });
+            };
+            message
+                .add_to(&mut payload, timestamp, &mut warn)
+                .with_context(|| format!("message {index}"))?;
+        }
+        Ok((payload, warnings))
+    }
+
+    /// Return the paths of all files the messages are loaded from
+    ///
+    /// For [`Content::FrameFiles`] the directory containing the frames is returned.
+    #[must_use]
+    pub fn files(&self) -> Vec<PathBuf> {
+        self.messages
+            .iter()
+            .flat_map(|message| message.content.files())
+            .collect()
+    }
+
+    /// Resolve the relative paths of all files the messages are loaded from against `directory`
+    ///
+    /// Used for configs that are loaded relative to another file, e.g. the entries of a playlist.
+    pub fn resolve_paths(&mut self, directory: &Path) {
+        for message in &mut self.messages {
+            message.content.resolve_paths(directory);
+        }
+    }
+
+    /// Check the whole config without stopping at the first problem
+    ///
+    /// Every message is rendered (without touching any hardware) to report
+    /// the problems of all messages and the size of the resulting payload.
+    #[must_use]
+    pub fn validate(&self) -> Validation {
+        validate(self.brightness, self.messages.iter().map(Ok))
+    }
+}
+
+impl PartialConfig {
+    /// Check the whole config like [`BadgeConfig::validate`]
+    ///
+    /// Every message that could not be parsed is reported with the field causing the problem.
+    #[must_use]
+    pub fn validate(&self) -> Validation {
+        validate(self.brightness, self.messages.iter().map(Result::as_ref))
+    }
+}
+
+impl From<BadgeConfig> for PartialConfig {
+    fn from(config: BadgeConfig) -> Self {
+        Self {
+            brightness: config.brightness,
+            messages: config.messages.into_iter().map(Ok).collect(),
+        }
+    }
+}
+
+/// Check all messages, see [`BadgeConfig::validate`]
+#[allow(clippy::missing_panics_doc)] // should never panic
+fn validate<'a>(
+    brightness: Brightness,
+    messages: impl ExactSizeIterator<Item = Result<&'a MessageSpec, &'a FieldError>>,
+) -> Validation {
+    let mut diagnostics = Vec::new();
+    if messages.len() > 8 {
+        diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            message: None,
+            field: Some("message"),
+            text: format!(
+                "expected at most 8 messages, found {} messages",
+                messages.len()
+            ),
+        });
+    }
+
+    let timestamp = OffsetDateTime::now_utc();
+    let mut payload = PayloadBuffer::with_timestamp(timestamp);
+    payload.set_brightness(brightness);
+
+    let mut columns = Vec::new();
+    for (index, message) in messages.enumerate() {
+        let message = match message {
+            Ok(message) => message,
+            Err(error) => {
+                diagnostics.push(Diagnostic {
+                    severity: Severity::Error,
+                    message: Some(index),
+                    field: error.field,
+                    text: error.text.clone(),
+                });
+                columns.push(None);
+                continue;
+            }
+        };
+        let problems = message.check();
+        if !problems.is_empty() {
+            diagnostics.extend(problems.into_iter().map(|(field, text)| Diagnostic {
+                severity: Severity::Error,
+                message: Some(index),
+                field: Some(field),
+                text,
+            }));
+            columns.push(None);
+            continue;
+        }
+
+        // messages that don't fit into the payload are checked on their own
+        let mut overflow = PayloadBuffer::new();
+        let target = if payload.num_messages() < 8 {
+            &mut payload
+        } else {
+            &mut overflow
+        };
+        let mut warn = |text| {
+            diagnostics.push(Diagnostic {
+                severity: Severity::Warning,
+                message: Some(index),
+                field: Some(message.content.field()),
+                text,
+            });
+        };
+        match message.add_to(target, timestamp, &mut warn) {
+            Ok(()) => columns.push(target.messages().last().map(|message| message.width())),
+            Err(error) => {
+                diagnostics.push(Diagnostic {
+                    severity: Severity::Error,
+                    message: Some(index),
+                    field: Some(message.content.field()),
+                    text: format!("{error:#}"),
+                });
+                columns.push(None);
+            }
+        }
+    }
+
+    let payload_size = payload.into_padded_bytes().as_ref().len();
+    if payload_size > MAX_PAYLOAD_SIZE {
+        diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            message: None,
+            field: None,
+            text: format!(
+                "payload too large: {payload_size} bytes, the badge supports at most \
+                     {MAX_PAYLOAD_SIZE} bytes"
+            ),
+        });
+    }
+
+    Validation {
+        columns,
+        payload_size,
+        diagnostics,
+    }
+}
+
+impl Content {
+    /// Return the name of the field that selects the variant
+    #[must_use]
+    pub fn field(&self) -> &'static str {
+        match self {
+            Self::Text { .. } => "text",
+            Self::Lines { .. } => "lines",
+            Self::Bitstring { .. } => "bitstring",
+            Self::Hex { .. } => "hex",
+            Self::BitmapBase64 { .. } => "bitmap_base64",
+            Self::BitmapFile { .. } => "bitmap_file",
+            Self::ImageFile { .. } => "img_file",
+            #[cfg(feature = "svg")]
+            Self::SvgFile { .. } => "svg_file",
+            Self::GifFile { .. } => "gif_file",
+            Self::ApngFile { .. } => "apng_file",
+            Self::WebpFile { .. } => "webp_file",
+            Self::FrameFiles { .. } => "frame_files",
+        }
+    }
+
+    /// Resolve the relative paths of the files the content is loaded from against `directory`
+    fn resolve_paths(&mut self, directory: &Path) {
+        match self {
+            #[cfg(feature = "template")]
+            Self::Text {
+                text,
+                template: true,
+                ..
+            } => *text = template::resolve_files(text, directory),
+            Self::BitmapFile {
+                bitmap_file: path, ..
+            }
+            | Self::ImageFile { img_file: path, .. }
+            | Self::GifFile { gif_file: path, .. }
+            | Self::ApngFile {
+                apng_file: path, ..
+            }
+            | Self::WebpFile {
+                webp_file: path, ..
+            } => *path = directory.join(&*path),
+            #[cfg(feature = "svg")]
+            Self::SvgFile { svg_file, .. } => *svg_file = directory.join(&*svg_file),
+            Self::FrameFiles { frame_files, .. } if Path::new(frame_files).is_relative() => {
+                // the directory must not be interpreted as part of the pattern
+                let directory = glob::Pattern::escape(&directory.to_string_lossy());
+                *frame_files = Path::new(&directory)
+                    .join(&*frame_files)
+                    .to_string_lossy()
+                    .into_owned();
+            }
+            _ => {}
+        }
+    }
+
+    /// Return the paths of the files the content is loaded from
+    fn files(&self) -> Vec<PathBuf> {
+        match self {
+            #[cfg(feature = "template")]
+            Self::Text {
+                text,
+                template: true,
+                ..
+            } => template::files(text),
+            Self::BitmapFile {
+                bitmap_file: path, ..
+            }
+            | Self::ImageFile { img_file: path, .. }
+            | Self::GifFile { gif_file: path, .. }
+            | Self::ApngFile {
+                apng_file: path, ..
+            }
+            | Self::WebpFile {
+                webp_file: path, ..
+            } => vec![path.clone()],
+            #[cfg(feature = "svg")]
+            Self::SvgFile { svg_file, .. } => vec![svg_file.clone()],
+            Self::FrameFiles { frame_files, .. } => {
+                // the directory of a glob pattern ends before the first wildcard
+                let directory: PathBuf = Path::new(frame_files)
+                    .components()
+                    .take_while(|component| {
+                        !component
+                            .as_os_str()
+                            .to_string_lossy()
+                            .contains(['*', '?', '['])
+                    })
+                    .collect();
+                if directory.as_os_str().is_empty() {
+                    vec![".".into()]
+                } else {
+                    vec![directory]
+                }
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl MessageSpec {
+    /// Create a message with the default style
+    #[must_use]
+    pub fn new(content: Content) -> Self {
+        Self {
+            blink: false,
+            border: false,
+            negative: false,
+            speed: None,
+            mode: Mode::default(),
+            content,
+        }
+    }
+
+    /// Return the style of the message
+    pub fn style(&self) -> Style {
+        let mut style = Style::default();
+        if self.blink {
+            style = style.blink();
+        }
+        if self.border {
+            style = style.border();
+        }
+        if self.negative {
+            style = style.invert();
+        }
+        style
+            .with_speed(self.speed.unwrap_or_default())
+            .with_mode(self.mode)
+    }
+
+    /// Check the options that can be validated without loading any files
+    ///
+    /// Returns the name of the field and the description of every problem.
+    fn check(&self) -> Vec<(&'static str, String)> {
+        let mut problems = Vec::new();
+        match &self.content {
+            Content::Lines { lines, align } => {
+                if !(1..=2).contains(&lines.len()) {
+                    problems.push((
+                        "lines",
+                        format!("expected 1 or 2 lines, found {} lines", lines.len()),
+                    ));
+                }
+                if align.len() > lines.len() {
+                    problems.push((
+                        "align",
+                        format!(
+                            "expected at most one alignment per line, \
+                             found {} alignments for {} lines",
+                            align.len(),
+                            lines.len()
+                        ),
+                    ));
+                }
+            }
+            Content::Bitstring { bitstring } => {
+                let lines: Vec<_> = bitstring.trim().lines().collect();
+                if lines.len() != DISPLAY_HEIGHT {
+                    problems.push((
+                        "bitstring",
+                        format!(
+                            "expected {DISPLAY_HEIGHT} lines in bitstring, found {} lines",
+                            lines.len()
+                        ),
+                    ));
+                }
+                let widths: Vec<_> = lines.iter().map(|line| line.chars().count()).collect();
+                if widths.iter().any(|&width| width != widths[0]) {
+                    problems.push((
+                        "bitstring",
+                        format!("lines should have the same length, got: {widths:?}"),
+                    ));
+                }
+                let invalid: Vec<_> = lines
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(y, line)| {
+                        line.chars()
+                            .enumerate()
+                            .filter(|&(_, c)| c != '_' && c != 'X')
+                            .map(move |(x, c)| format!("({x}, {y}): {c:?}"))
+                    })
+                    .collect();
+                if !invalid.is_empty() {
+                    problems.push((
+                        "bitstring",
+                        format!("invalid bit values for bits {}", invalid.join(", ")),
+                    ));
+                }
+            }
+            Content::Hex { hex } => {
+                if let Err(error) = Bitmap::from_hex(hex) {
+                    problems.push(("hex", format!("{error:#}")));
+                }
+            }
+            Content::BitmapBase64 {
+                width,
+                bitmap_base64,
+            } => {
+                if *width == 0 {
+                    problems.push(("width", "width must be greater than 0".into()));
+                }
+                match decode_base64(bitmap_base64) {
+                    Ok(data) => {
+                        if let Err(error) = check_bitmap_size(&data, *width) {
+                            problems.push(("bitmap_base64", format!("{error:#}")));
+                        }
+                    }
+                    Err(error) => {
+                        problems.push(("bitmap_base64", format!("decode bitmap: {error}")));
+                    }
+                }
+            }
+            Content::BitmapFile { width, .. } => {
+                if *width == 0 {
+                    problems.push(("width", "width must be greater than 0".into()));
+                }
+            }
+            Content::GifFile { layout, .. }
+            | Content::ApngFile { layout, .. }
+            | Content::WebpFile { layout, .. }
+            | Content::FrameFiles { layout, .. } => {
+                // every frame fills the whole display
+                for (field, unsupported) in [
+                    ("width", layout.width.is_some()),
+                    ("padding_left", layout.padding_left != 0),
+                    ("padding_right", layout.padding_right != 0),
+                ] {
+                    if unsupported {
+                        problems.push((
+                            field,
+                            format!(
+                                "`{field}` is not supported for animations, \
+                                 every frame fills the whole display"
+                            ),
+                        ));
+                    }
+                }
+            }
+            Content::Text { template, .. } => {
+                if *template && cfg!(not(feature = "template")) {
+                    problems.push((
+                        "template",
+                        "placeholders are not supported, enable the `template` feature".into(),
+                    ));
+                }
+            }
+            Content::ImageFile { .. } => {}
+            #[cfg(feature = "svg")]
+            Content::SvgFile { .. } => {}
+        }
+        problems
+    }
+
+    /// Render the message and add it to the `payload`
+    #[cfg_attr(not(feature = "template"), allow(unused_variables))]
+    fn add_to(
+        &self,
+        payload: &mut PayloadBuffer,
+        timestamp: OffsetDateTime,
+        warn: &mut impl FnMut(String),
+    ) -> Result<()> {
+        if let Some((_, problem)) = self.check().into_iter().next() {
+            anyhow::bail!(problem);
+        }
+
+        let style = self.style();
+        match &self.content {
+            Content::Text {
+                text,
+                fit,
+                wrap,
+                template,
+            } => {
+                #[cfg(feature = "template")]
+                let text = &if *template {
+                    template::render(text, timestamp)?
+                } else {
+                    text.clone()
+                };
+                if *fit {
+                    if let Some(text) = FittedText::new(text, *wrap) {
+                        payload.add_message_drawable(style, &text);
+                        return Ok(());
+                    }
+                    warn(format!(
+                        "text does not fit the display without scrolling: {text:?}"
+                    ));
+                }
+                let text = Text::new(
+                    text,
+                    Point::new(0, 8),
+                    U8g2TextStyle::new(u8g2_font_lucasfont_alternate_tf, BinaryColor::On),
+                );
+                payload.add_message_drawable(style, &text);
+            }
+            Content::Lines { lines, align } => {
+                let line = |i: usize| lines.get(i).map_or("", String::as_str);
+                let align = |i: usize| align.get(i).copied().unwrap_or_default();
+                let text = TwoLines::new(line(0), line(1)).align(align(0), align(1));
+                payload.add_message_drawable(style, &text);
+            }
+            Content::Bitstring { bitstring } => {
+                let lines: Vec<_> = bitstring.trim().lines().collect();
+                let mut buffer = payload.add_message(style, lines[0].len().div_ceil(8));
+                for (y, line) in lines.iter().enumerate() {
+                    for (x, c) in line.chars().enumerate() {
+                        if c == 'X' {
+                            buffer.set((x, y), State::On);
+                        }
+                    }
+                }
+            }
+            Content::Hex { hex } => {
+                payload.add_message_hex(style, hex)?;
+            }
+            Content::BitmapBase64 {
+                width,
+                bitmap_base64: bitmap,
+            } => {
+                let data = decode_base64(bitmap).context("decode bitmap")?;
+                check_bitmap_size(&data, *width)?;
+                let image_raw = ImageRawLE::<BinaryColor>::new(&data, *width);
+                let image = Image::new(&image_raw, Point::zero());
+                payload.add_message_drawable(style, &image);
+            }
+            Content::BitmapFile { width, bitmap_file } => {
+                let data = fs::read(bitmap_file).context("load bitmap")?;
+                check_bitmap_size(&data, *width).context("load bitmap")?;
+                let image_raw = ImageRawLE::<BinaryColor>::new(&data, *width);
+                let image = Image::new(&image_raw, Point::zero());
+                payload.add_message_drawable(style, &image);
+            }
+            Content::ImageFile {
+                img_file,
+                layout,
+                raster,
+            } => {
+                let img = ImageReader::open(img_file)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|reader| Ok(reader.decode()?))
+                    .with_context(|| format!("load image file: {img_file:?}"))?;
+                payload.add_message_drawable(style, &layout.render(&img, raster));
+            }
+            #[cfg(feature = "svg")]
+            Content::SvgFile { svg_file, raster } => {
+                let data =
+                    fs::read(svg_file).with_context(|| format!("load svg file: {svg_file:?}"))?;
+                let image = svg::render(&data, raster)
+                    .with_context(|| format!("render svg file: {svg_file:?}"))?;
+                payload.add_message_drawable(style, &image);
+            }
+            Content::GifFile {
+                gif_file: path,
+                layout,
+                raster,
+            } => {
+                let animation =
+                    Animation::gif(path).with_context(|| format!("load gif file: {path:?}"))?;
+                animation
+                    .render(*layout, *raster)
+                    .add_message(payload, style, self, warn)?;
+            }
+            Content::ApngFile {
+                apng_file: path,
+                layout,
+                raster,
+            } => {
+                let animation =
+                    Animation::apng(path).with_context(|| format!("load apng file: {path:?}"))?;
+                animation
+                    .render(*layout, *raster)
+                    .add_message(payload, style, self, warn)?;
+            }
+            Content::WebpFile {
+                webp_file: path,
+                layout,
+                raster,
+            } => {
+                let animation =
+                    Animation::webp(path).with_context(|| format!("load webp file: {path:?}"))?;
+                animation
+                    .render(*layout, *raster)
+                    .add_message(payload, style, self, warn)?;
+            }
+            Content::FrameFiles {
+                frame_files: pattern,
+                layout,
+                raster,
+            } => {
+                let animation = Animation::frame_files(pattern)
+                    .with_context(|| format!("load frame files: {pattern:?}"))?;
+                animation
+                    .render(*layout, *raster)
+                    .add_message(payload, style, self, warn)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Frames of an animation loaded from a file
+struct Animation<F = DynamicImage> {
+    frames: Vec<F>,
+
+    /// Display duration of each frame, if the source provides timing information
+    delays: Option<Vec<Duration>>,
+}
+
+impl Animation {
+    fn gif(path: &Path) -> Result<Self> {
+        let decoder = GifDecoder::new(BufReader::new(fs::File::open(path)?))?;
+        Self::from_frames(decoder.into_frames())
+    }
+
+    fn apng(path: &Path) -> Result<Self> {
+        let decoder = PngDecoder::new(BufReader::new(fs::File::open(path)?))?;
+        if decoder.is_apng()? {
+            Self::from_frames(decoder.apng()?.into_frames())
+        } else {
+            Ok(Self::still(DynamicImage::from_decoder(decoder)?))
+        }
+    }
+
+    fn webp(path: &Path) -> Result<Self> {
+        let decoder = WebPDecoder::new(BufReader::new(fs::File::open(path)?))?;
+        if decoder.has_animation() {
+            Self::from_frames(decoder.into_frames())
+        } else {
+            Ok(Self::still(DynamicImage::from_decoder(decoder)?))
+        }
+    }
+
+    /// Load numbered frames from a directory or a glob pattern (e.g. `frames/*.png`)
+    fn frame_files(pattern: &str) -> Result<Self> {
+        let mut paths = if Path::new(pattern).is_dir() {
+            fs::read_dir(pattern)?
+                .map(|entry| Ok(entry?.path()))
+                .collect::<Result<Vec<_>>>()?
+        } else {
+            glob::glob(pattern)?.collect::<Result<Vec<_>, _>>()?
+        };
+        // skip other files, e.g. `.DS_Store` or a `README` next to the frames
+        paths.retain(|path| {
+            path.is_file()
+                && ImageFormat::from_path(path).is_ok_and(|format| format.reading_enabled())
+        });
+        anyhow::ensure!(!paths.is_empty(), "no frame files found");
+        paths.sort_by_cached_key(|path| natural_sort_key(path));
+
+        let frames = paths
+            .iter()
+            .map(|path| {
+                ImageReader::open(path)
+                    .and_then(ImageReader::with_guessed_format)?
+                    .decode()
+                    .with_context(|| format!("load frame: {path:?}"))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            frames,
+            delays: None,
+        })
+    }
+
+    fn still(image: DynamicImage) -> Self {
+        Self {
+            frames: vec![image],
+            delays: None,
+        }
+    }
+
+    fn from_frames(frames: Frames) -> Result<Self> {
+        let frames = frames.collect_frames()?;
+        anyhow::ensure!(!frames.is_empty(), "animation has no frames");
+
+        let delays = frames
+            .iter()
+            .map(|frame| {
+                let delay = Duration::from(frame.delay());
+                // browsers use a delay of 100ms for very short or missing delays
+                if delay <= Duration::from_millis(10) {
+                    Duration::from_millis(100)
+                } else {
+                    delay
+                }
+            })
+            .collect();
+        Ok(Self {
+            frames: frames
+                .into_iter()
+                .map(|frame| frame.into_buffer().into())
+                .collect(),
+            delays: Some(delays),
+        })
+    }
+
+    /// Place and convert the frames for the 44x11 pixel display
+    fn render(self, layout: ImageLayout, raster: RasterOptions) -> Animation<BinaryImage> {
+        let layout = ImageLayout {
+            width: Some(FRAME_WIDTH),
+            ..layout
+        };
+        Animation {
+            frames: self
+                .frames
+                .iter()
+                .map(|frame| layout.render(frame, &raster))
+                .collect(),
+            delays: self.delays,
+        }
+    }
+}
+
+impl Animation<BinaryImage> {
+    /// Add the frames as a new message
+    ///
+    /// In [`Mode::Fast`] the frames are resampled to the speed of the badge.
+    /// If the speed is not set, the speed closest to the frame delays is used.
+    /// Frames are skipped if the animation does not fit into the payload.
+    fn add_message(
+        self,
+        payload: &mut PayloadBuffer,
+        mut style: Style,
+        message: &MessageSpec,
+        warn: &mut impl FnMut(String),
+    ) -> Result<()> {
+        let mut frames: FrameSequence<_> = self.frames.into_iter().collect();
+
+        if let (Mode::Fast, Some(delays)) = (message.mode, &self.delays) {
+            let speed = message.speed.unwrap_or_else(|| {
+                let shortest = delays.iter().min().copied().unwrap_or_default();
+                Speed::nearest(1.0 / shortest.as_secs_f32())
+            });
+            style = style.with_speed(speed);
+            frames = animation::resample(delays, speed)
+                .into_iter()
+                .map(|i| frames.frames()[i].clone())
+                .collect();
+        }
+
+        let max_frames = animation::max_frames(payload);
+        anyhow::ensure!(max_frames > 0, "no space left in the payload");
+        if frames.len() > max_frames {
+            warn(format!(
+                "animation has {} frames, only {max_frames} fit into the payload, skipping frames",
+                frames.len(),
+            ));
+            frames.reduce(max_frames);
+        }
+
+        payload.add_message_drawable(style, &frames);
+        Ok(())
+    }
+}
+
+/// Check that a raw bitmap consists of complete rows and fits the display height
+///
+/// Every row starts at a new byte.
+fn check_bitmap_size(data: &[u8], width: u32) -> Result<()> {
+    let row_len = usize::try_from(width.div_ceil(8))?;
+    if row_len == 0 {
+        return Ok(());
+    }
+    anyhow::ensure!(
+        data.len().is_multiple_of(row_len),
+        "bitmap size of {} bytes is not a multiple of the row size ({row_len} bytes for width {width})",
+        data.len()
+    );
+    let rows = data.len() / row_len;
+    anyhow::ensure!(
+        rows <= DISPLAY_HEIGHT,
+        "bitmap has {rows} rows with width {width}, the display has {DISPLAY_HEIGHT} rows"
+    );
+    Ok(())
+}
+
+/// Decode base64 with or without padding
+fn decode_base64(data: &str) -> Result<Vec<u8>, base64::DecodeError> {
+    if data.ends_with('=') {
+        base64::engine::general_purpose::STANDARD
+    } else {
+        base64::engine::general_purpose::STANDARD_NO_PAD
+    }
+    .decode(data)
+}
+
+/// Sort key that orders numbers by their value (`frame2` before `frame10`)
+fn natural_sort_key(path: &Path) -> Vec<(String, u64)> {
+    let mut key = Vec::new();
+    let mut rest = path.to_string_lossy().into_owned();
+    while !rest.is_empty() {
+        let text_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let text: String = rest.drain(..text_len).collect();
+        let digits_len = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let number = rest.drain(..digits_len).collect::<String>();
+        key.push((text, number.parse().unwrap_or(u64::MAX)));
+    }
+    key
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Animation, BadgeConfig, Content, MessageSpec, PartialConfig, Severity};
+    use crate::{
+        protocol::{Brightness, Mode, Speed, Style},
+        util::{
+            raster::{ImageLayout, RasterOptions},
+            text::Alignment,
+        },
+    };
+
+    #[test]
+    fn round_trip_config() {
+        let config = BadgeConfig {
+            brightness: Brightness::Half,
+            messages: vec![
+                MessageSpec {
+                    blink: true,
+                    negative: true,
+                    speed: Some(Speed::Fps7_5),
+                    mode: Mode::Center,
+                    ..MessageSpec::new(Content::Lines {
+                        lines: vec!["top".into(), "bottom".into()],
+                        align: Vec::new(),
+                    })
+                },
+                MessageSpec::new(Content::ImageFile {
+                    img_file: "logo.png".into(),
+                    layout: ImageLayout::default(),
+                    raster: RasterOptions::default(),
+                }),
+            ],
+        };
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(serde_json::from_str::<BadgeConfig>(&json).unwrap(), config);
+
+        assert_eq!(
+            config.messages[0].style(),
+            Style::default()
+                .blink()
+                .invert()
+                .with_speed(Speed::Fps7_5)
+                .with_mode(Mode::Center)
+        );
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn demo_configs_match_schema() {
+        let schema = serde_json::to_value(super::json_schema()).unwrap();
+        let validator = jsonschema::validator_for(&schema).unwrap();
+
+        let mut count = 0;
+        for entry in std::fs::read_dir("demo").unwrap() {
+            let path = entry.unwrap().path();
+            let config: serde_json::Value =
+                toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+            if let Err(error) = validator.validate(&config) {
+                panic!("{path:?} does not match the schema: {error}");
+            }
+            count += 1;
+        }
+        assert!(count > 0);
+
+        // configs that are rejected by serde have to be rejected by the schema as well
+        for message in [
+            serde_json::json!({"text": "a", "color": "red"}),
+            serde_json::json!({"text": "a", "blnk": true}),
+            serde_json::json!({"text": "a", "img_file": "logo.png"}),
+            serde_json::json!({"img_file": "logo.png", "threshold": 300}),
+            serde_json::json!({"bitmap_base64": "AA", "width": 8, "bitmap_file": "x.bin"}),
+            serde_json::json!({"fit": true}),
+        ] {
+            let invalid = serde_json::json!({ "message": [message] });
+            assert!(serde_json::from_value::<BadgeConfig>(invalid.clone()).is_err());
+            assert!(
+                !validator.is_valid(&invalid),
+                "{invalid} matches the schema"
+            );
+        }
+    }
+
+    #[test]
+    fn ron_struct_syntax() {
+        let options = ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME);
+        let structs: BadgeConfig = options
+            .from_str(
+                r#"(message: [
+                    (text: "Hello", mode: "center", speed: 3),
+                    (img_file: "logo.png", threshold: "otsu", crop: (x: 0, y: 0, width: 8, height: 4)),
+                ])"#,
+            )
+            .unwrap();
+        let maps: BadgeConfig = options
+            .from_str(
+                r#"(message: [
+                    {"text": "Hello", "mode": "center", "speed": 3},
+                    {"img_file": "logo.png", "threshold": "otsu", "crop": {"x": 0, "y": 0, "width": 8, "height": 4}},
+                ])"#,
+            )
+            .unwrap();
+        assert_eq!(structs, maps);
+        assert_eq!(structs.messages[0].mode, Mode::Center);
+        assert_eq!(structs.messages[0].speed, Some(Speed::Fps2_4));
+    }
+
+    #[test]
+    fn render_reports_message_index() {
+        let config = BadgeConfig {
+            messages: vec![
+                MessageSpec::new(Content::Text {
+                    text: "this text is way too long for the display".into(),
+                    fit: true,
+                    wrap: false,
+                    template: false,
+                }),
+                MessageSpec::new(Content::Bitstring {
+                    bitstring: "X_".into(),
+                }),
+            ],
+            ..BadgeConfig::default()
+        };
+        let error = config.render_with_warnings().unwrap_err();
+        assert_eq!(
+            format!("{error:#}"),
+            "message 1: expected 11 lines in bitstring, found 1 lines"
+        );
+
+        let config = BadgeConfig {
+            messages: config.messages[..1].to_vec(),
+            ..config
+        };
+        let (payload, warnings) = config.render_with_warnings().unwrap();
+        assert_eq!(payload.num_messages(), 1);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].message, 0);
+    }
+
+    #[test]
+    fn text_without_template_is_literal() {
+        let text = |text: &str, template| {
+            BadgeConfig {
+                messages: vec![MessageSpec::new(Content::Text {
+                    text: text.into(),
+                    fit: false,
+                    wrap: false,
+                    template,
+                })],
+                ..BadgeConfig::default()
+            }
+            .render()
+            .map(|payload| payload.messages().next().unwrap().to_bitmap())
+        };
+        assert!(text("a } b {x}", false).unwrap().width() > 0);
+
+        let template = text("{{x}}", true);
+        #[cfg(feature = "template")]
+        assert_eq!(template.unwrap(), text("{x}", false).unwrap());
+        #[cfg(not(feature = "template"))]
+        assert!(template.is_err());
+    }
+
+    #[test]
+    fn hex_content() {
+        let config: BadgeConfig = toml::from_str(
+            r#"
+            [[message]]
+            hex = """
+            00386CC6C6FEC6C6C6C600
+            00FC6666667C6666FC0000
+            """
+
+            [[message]]
+            hex = "00386CC6C6FEC6C6C6C6"
+            "#,
+        )
+        .unwrap();
+        let validation = config.validate();
+        assert_eq!(validation.columns, [Some(16), None]);
+        assert_eq!(
+            validation.diagnostics[0].to_string(),
+            "message 1, `hex`: expected 22 hex digits per group of 8 columns, found 20 hex digits"
+        );
+
+        let config = BadgeConfig {
+            messages: config.messages[..1].to_vec(),
+            ..config
+        };
+        let payload = config.render().unwrap();
+        assert_eq!(
+            payload.messages().next().unwrap().to_hex(),
+            "00386CC6C6FEC6C6C6C60000FC6666667C6666FC0000"
+        );
+    }
+
+    #[test]
+    fn validate_reports_all_problems() {
+        let bitstring = |bitstring: &str| {
+            MessageSpec::new(Content::Bitstring {
+                bitstring: bitstring.into(),
+            })
+        };
+        let mut messages = vec![
+            bitstring(&"X_X\n".repeat(11)),
+            bitstring("X_\nXa_\n"),
+            MessageSpec::new(Content::Lines {
+                lines: Vec::new(),
+                align: vec![Alignment::Left],
+            }),
+            MessageSpec::new(Content::ImageFile {
+                img_file: "does-not-exist.png".into(),
+                layout: ImageLayout::default(),
+                raster: RasterOptions::default(),
+            }),
+        ];
+        let block = |width| bitstring(&format!("{}\n", "X".repeat(width)).repeat(11));
+        messages.extend(std::iter::repeat_n(block(8), 7));
+        let config = BadgeConfig {
+            messages,
+            ..BadgeConfig::default()
+        };
+
+        let validation = config.validate();
+        assert!(!validation.is_valid());
+        assert_eq!(validation.columns.len(), 11);
+        assert_eq!(validation.columns[0], Some(8));
+        assert_eq!(validation.columns[1..4], [None, None, None]);
+        assert_eq!(validation.columns[10], Some(8));
+        // the header and 8 messages with one column group each, padded to 64 bytes
+        assert_eq!(validation.payload_size, 192);
+
+        let problems: Vec<_> = validation
+            .diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.message, diagnostic.field))
+            .collect();
+        assert_eq!(
+            problems,
+            [
+                (None, Some("message")),
+                (Some(1), Some("bitstring")),
+                (Some(1), Some("bitstring")),
+                (Some(1), Some("bitstring")),
+                (Some(2), Some("lines")),
+                (Some(2), Some("align")),
+                (Some(3), Some("img_file")),
+            ]
+        );
+        assert_eq!(
+            validation.diagnostics[3].to_string(),
+            "message 1, `bitstring`: invalid bit values for bits (1, 1): 'a'"
+        );
+        assert!(validation
+            .diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.severity == Severity::Error));
+
+        let config = BadgeConfig {
+            messages: vec![block(8 * 800)],
+            ..BadgeConfig::default()
+        };
+        let validation = config.validate();
+        assert_eq!(validation.columns, [Some(8 * 800)]);
+        assert_eq!(validation.payload_size, 8896);
+        assert_eq!(
+            validation.diagnostics[0].to_string(),
+            "payload too large: 8896 bytes, the badge supports at most 8192 bytes"
+        );
+    }
+
+    #[test]
+    fn parse_errors_name_message_and_field() {
+        let source = r#"
+            [[message]]
+            text = "valid"
+
+            [[message]]
+            text = "typo"
+            blnk = true
+
+            [[message]]
+            img_file = "logo.png"
+            threshold = 300
+
+            [[message]]
+            text = "two contents"
+            img_file = "logo.png"
+        "#;
+        let config: PartialConfig = toml::from_str(source).unwrap();
+        let validation = config.validate();
+        assert_eq!(validation.columns, [Some(24), None, None, None]);
+        let problems: Vec<_> = validation
+            .diagnostics
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            problems,
+            [
+                "message 1: unknown field `blnk`, expected one of `blink`, `border`, `negative`, \
+                 `speed`, `mode`, `text`, `fit`, `wrap`, `template`",
+                "message 2, `threshold`: expected a brightness from 0 to 255 or \"otsu\"",
+                "message 3, `img_file`: `text` and `img_file` can't be used in the same message",
+            ]
+        );
+        assert_eq!(validation.diagnostics[2].field, Some("img_file"));
+
+        // a config that is rendered stops at the first invalid message
+        let error = toml::from_str::<BadgeConfig>(source).unwrap_err();
+        assert!(error
+            .message()
+            .starts_with("message 1: unknown field `blnk`"));
+    }
+
+    #[test]
+    fn validate_bitmap_size() {
+        let bitmap = |width, bitmap_base64: &str| {
+            MessageSpec::new(Content::BitmapBase64 {
+                width,
+                bitmap_base64: bitmap_base64.into(),
+            })
+        };
+        let config = BadgeConfig {
+            messages: vec![
+                // 12 bytes: 12 rows of 8 pixels
+                bitmap(8, "AAAAAAAAAAAAAAAA"),
+                // 3 bytes: not a multiple of the 2 byte rows
+                bitmap(16, "AAAA"),
+                bitmap(16, &"A".repeat(30)),
+            ],
+            ..BadgeConfig::default()
+        };
+        let validation = config.validate();
+        assert_eq!(validation.columns, [None, None, Some(16)]);
+        let problems: Vec<_> = validation
+            .diagnostics
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            problems,
+            [
+                "message 0, `bitmap_base64`: bitmap has 12 rows with width 8, the display has 11 rows",
+                "message 1, `bitmap_base64`: bitmap size of 3 bytes is not a multiple of the row size (2 bytes for width 16)",
+            ]
+        );
+        assert!(config.render().is_err());
+    }
+
+    #[test]
+    fn referenced_files() {
+        let frame_files = |frame_files: &str| {
+            MessageSpec::new(Content::FrameFiles {
+                frame_files: frame_files.into(),
+                layout: ImageLayout::default(),
+                raster: RasterOptions::default(),
+            })
+        };
+        let config = BadgeConfig {
+            messages: vec![
+                MessageSpec::new(Content::ImageFile {
+                    img_file: "logo.png".into(),
+                    layout: ImageLayout::default(),
+                    raster: RasterOptions::default(),
+                }),
+                MessageSpec::new(Content::Bitstring {
+                    bitstring: "X".into(),
+                }),
+                frame_files("animation/frames/*.png"),
+                frame_files("frame?.png"),
+            ],
+            ..BadgeConfig::default()
+        };
+        assert_eq!(
+            config.files(),
+            ["logo.png", "animation/frames", "."].map(std::path::PathBuf::from)
+        );
+
+        let mut resolved = config.clone();
+        resolved.resolve_paths(std::path::Path::new("playlist"));
+        assert_eq!(
+            resolved.files(),
+            ["playlist/logo.png", "playlist/animation/frames", "playlist"]
+                .map(std::path::PathBuf::from)
+        );
+    }
+
+    #[test]
+    fn resolve_paths_relative_to_directory() {
+        // brackets in the directory must not be read as a glob pattern
+        let directory =
+            std::env::temp_dir().join(format!("badgemagic-resolve-[{}]", std::process::id()));
+        std::fs::create_dir_all(directory.join("frames")).unwrap();
+        let frame = image::GrayImage::from_pixel(4, 11, image::Luma([255]));
+        for name in ["frames/0.png", "frames/1.png", "logo.png"] {
+            frame.save(directory.join(name)).unwrap();
+        }
+
+        let mut config: BadgeConfig = toml::from_str(
+            r#"
+            [[message]]
+            img_file = "logo.png"
+
+            [[message]]
+            frame_files = "frames/*.png"
+            "#,
+        )
+        .unwrap();
+        assert!(config.render().is_err());
+        config.resolve_paths(&directory);
+        let payload = config.render().unwrap();
+        assert_eq!(payload.num_messages(), 2);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn frame_files_skip_other_files() {
+        let directory =
+            std::env::temp_dir().join(format!("badgemagic-frames-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let frame = image::GrayImage::from_pixel(4, 11, image::Luma([255]));
+        for name in ["frame1.png", "frame2.bmp"] {
+            frame.save(directory.join(name)).unwrap();
+        }
+        for name in [".DS_Store", "README", "notes.txt"] {
+            std::fs::write(directory.join(name), "not an image").unwrap();
+        }
+
+        let animation = Animation::frame_files(&directory.to_string_lossy()).unwrap();
+        assert_eq!(animation.frames.len(), 2);
+        let pattern = directory.join("*");
+        let animation = Animation::frame_files(&pattern.to_string_lossy()).unwrap();
+        assert_eq!(animation.frames.len(), 2);
+
+        std::fs::remove_file(directory.join("frame1.png")).unwrap();
+        std::fs::remove_file(directory.join("frame2.bmp")).unwrap();
+        let error = Animation::frame_files(&directory.to_string_lossy()).err();
+        assert_eq!(error.unwrap().to_string(), "no frame files found");
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,366 @@
+//! Deserialization of messages, with errors that name the field causing them
+//!
+//! A message is buffered as a map first. The content variant is selected by its key
+//! (e.g. `text` or `img_file`) and only the fields of that variant are accepted.
+
+use std::fmt::{self, Display};
+
+use serde::{
+    de::{self, DeserializeOwned, DeserializeSeed, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use serde_json::{Map, Value};
+
+use super::{Content, MessageSpec};
+
+/// Keys that select the variant of [`Content`], in the order of the variants
+const SELECTORS: &[&str] = &[
+    "text",
+    "lines",
+    "bitstring",
+    "hex",
+    "bitmap_base64",
+    "bitmap_file",
+    "img_file",
+    "svg_file",
+    "gif_file",
+    "apng_file",
+    "webp_file",
+    "frame_files",
+];
+
+/// Problem found while parsing a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    /// Name of the field, `None` if no known field is affected (e.g. for unknown fields)
+    pub field: Option<&'static str>,
+
+    pub text: String,
+}
+
+impl FieldError {
+    fn new(field: &'static str, text: impl Display) -> Self {
+        Self {
+            field: Some(field),
+            text: text.to_string(),
+        }
+    }
+}
+
+impl Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(field) = self.field {
+            write!(f, "`{field}`: ")?;
+        }
+        f.write_str(&self.text)
+    }
+}
+
+impl std::error::Error for FieldError {}
+
+/// Fields of a message that have not been parsed yet
+struct Fields {
+    map: Map<String, Value>,
+
+    /// Names of all fields that were looked up, reported for unknown fields
+    known: Vec<&'static str>,
+}
+
+impl Fields {
+    fn new(value: Value) -> Result<Self, FieldError> {
+        match value {
+            Value::Object(map) => Ok(Self {
+                map,
+                known: Vec::new(),
+            }),
+            _ => Err(FieldError {
+                field: None,
+                text: "expected a message with named fields".into(),
+            }),
+        }
+    }
+
+    fn contains(&self, field: &str) -> bool {
+        self.map.contains_key(field)
+    }
+
+    /// Remove and parse a field, `None` if it is missing
+    fn take<T: DeserializeOwned>(&mut self, field: &'static str) -> Result<Option<T>, FieldError> {
+        self.known.push(field);
+        self.map
+            .remove(field)
+            .map(|value| T::deserialize(value).map_err(|error| FieldError::new(field, error)))
+            .transpose()
+    }
+
+    fn required<T: DeserializeOwned>(&mut self, field: &'static str) -> Result<T, FieldError> {
+        self.take(field)?
+            .ok_or_else(|| FieldError::new(field, format_args!("missing field `{field}`")))
+    }
+
+    fn or_default<T: DeserializeOwned + Default>(
+        &mut self,
+        field: &'static str,
+    ) -> Result<T, FieldError> {
+        Ok(self.take(field)?.unwrap_or_default())
+    }
+
+    /// Parse a struct whose fields are part of the message, all its fields need a default
+    fn flattened<T: DeserializeOwned>(&mut self) -> Result<T, FieldError> {
+        let mut map = Map::new();
+        for &field in struct_fields::<T>() {
+            self.known.push(field);
+            if let Some(value) = self.map.remove(field) {
+                // parse the fields one by one to report which one is invalid
+                let single = Map::from_iter([(field.to_owned(), value.clone())]);
+                T::deserialize(Value::Object(single))
+                    .map_err(|error| FieldError::new(field, error))?;
+                map.insert(field.to_owned(), value);
+            }
+        }
+        T::deserialize(Value::Object(map)).map_err(|error| FieldError {
+            field: None,
+            text: error.to_string(),
+        })
+    }
+
+    /// Report the first field that was not parsed
+    fn finish(self) -> Result<(), FieldError> {
+        let Some(unknown) = self.map.keys().next() else {
+            return Ok(());
+        };
+        let mut known = self.known;
+        known.dedup();
+        let known: Vec<_> = known.iter().map(|field| format!("`{field}`")).collect();
+        Err(FieldError {
+            field: None,
+            text: format!(
+                "unknown field `{unknown}`, expected one of {}",
+                known.join(", ")
+            ),
+        })
+    }
+}
+
+impl MessageSpec {
+    /// Parse a message from a map of its fields
+    pub(super) fn from_value(value: Value) -> Result<Self, FieldError> {
+        let mut fields = Fields::new(value)?;
+        let message = Self {
+            blink: fields.or_default("blink")?,
+            border: fields.or_default("border")?,
+            negative: fields.or_default("negative")?,
+            speed: fields.take("speed")?,
+            mode: fields.or_default("mode")?,
+            content: Content::from_fields(&mut fields)?,
+        };
+        fields.finish()?;
+        Ok(message)
+    }
+}
+
+impl Content {
+    fn from_fields(fields: &mut Fields) -> Result<Self, FieldError> {
+        let selected: Vec<_> = SELECTORS
+            .iter()
+            .copied()
+            .filter(|selector| fields.contains(selector))
+            .collect();
+        let selector = match selected[..] {
+            [selector] => selector,
+            [] => {
+                let selectors: Vec<_> =
+                    SELECTORS.iter().map(|field| format!("`{field}`")).collect();
+                return Err(FieldError {
+                    field: None,
+                    text: format!(
+                        "missing content, expected one of the fields {}",
+                        selectors.join(", ")
+                    ),
+                });
+            }
+            [first, second, ..] => {
+                return Err(FieldError::new(
+                    second,
+                    format_args!("`{first}` and `{second}` can't be used in the same message"),
+                ))
+            }
+        };
+
+        Ok(match selector {
+            "text" => Self::Text {
+                text: fields.required("text")?,
+                fit: fields.or_default("fit")?,
+                wrap: fields.or_default("wrap")?,
+                template: fields.or_default("template")?,
+            },
+            "lines" => Self::Lines {
+                lines: fields.required("lines")?,
+                align: fields.or_default("align")?,
+            },
+            "bitstring" => Self::Bitstring {
+                bitstring: fields.required("bitstring")?,
+            },
+            "hex" => Self::Hex {
+                hex: fields.required("hex")?,
+            },
+            "bitmap_base64" => Self::BitmapBase64 {
+                width: fields.required("width")?,
+                bitmap_base64: fields.required("bitmap_base64")?,
+            },
+            "bitmap_file" => Self::BitmapFile {
+                width: fields.required("width")?,
+                bitmap_file: fields.required("bitmap_file")?,
+            },
+            "img_file" => Self::ImageFile {
+                img_file: fields.required("img_file")?,
+                layout: fields.flattened()?,
+                raster: fields.flattened()?,
+            },
+            #[cfg(feature = "svg")]
+            "svg_file" => Self::SvgFile {
+                svg_file: fields.required("svg_file")?,
+                raster: fields.flattened()?,
+            },
+            #[cfg(not(feature = "svg"))]
+            "svg_file" => {
+                return Err(FieldError::new(
+                    "svg_file",
+                    "vector images are not supported, enable the `svg` feature",
+                ))
+            }
+            "gif_file" => Self::GifFile {
+                gif_file: fields.required("gif_file")?,
+                layout: fields.flattened()?,
+                raster: fields.flattened()?,
+            },
+            "apng_file" => Self::ApngFile {
+                apng_file: fields.required("apng_file")?,
+                layout: fields.flattened()?,
+                raster: fields.flattened()?,
+            },
+            "webp_file" => Self::WebpFile {
+                webp_file: fields.required("webp_file")?,
+                layout: fields.flattened()?,
+                raster: fields.flattened()?,
+            },
+            "frame_files" => Self::FrameFiles {
+                frame_files: fields.required("frame_files")?,
+                layout: fields.flattened()?,
+                raster: fields.flattened()?,
+            },
+            _ => unreachable!("unhandled selector {selector:?}"),
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for MessageSpec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::from_value(Value::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Content {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut fields =
+            Fields::new(Value::deserialize(deserializer)?).map_err(de::Error::custom)?;
+        let content = Self::from_fields(&mut fields).map_err(de::Error::custom)?;
+        fields.finish().map_err(de::Error::custom)?;
+        Ok(content)
+    }
+}
+
+/// Parse the messages, errors name the index of the message
+pub(super) fn messages<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<MessageSpec>, D::Error> {
+    /// Parse a message, the error is created while deserializing the message to keep its location
+    struct Message(usize);
+
+    impl<'de> DeserializeSeed<'de> for Message {
+        type Value = MessageSpec;
+
+        fn deserialize<D: Deserializer<'de>>(
+            self,
+            deserializer: D,
+        ) -> Result<Self::Value, D::Error> {
+            MessageSpec::from_value(Value::deserialize(deserializer)?)
+                .map_err(|error| de::Error::custom(format_args!("message {}: {error}", self.0)))
+        }
+    }
+
+    deserializer.deserialize_seq(SeqVisitor(Message))
+}
+
+/// Parse every message on its own, invalid messages don't stop the others from being parsed
+pub(super) fn partial_messages<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Result<MessageSpec, FieldError>>, D::Error> {
+    struct Message;
+
+    impl<'de> DeserializeSeed<'de> for Message {
+        type Value = Result<MessageSpec, FieldError>;
+
+        fn deserialize<D: Deserializer<'de>>(
+            self,
+            deserializer: D,
+        ) -> Result<Self::Value, D::Error> {
+            Ok(MessageSpec::from_value(Value::deserialize(deserializer)?))
+        }
+    }
+
+    deserializer.deserialize_seq(SeqVisitor(|_| Message))
+}
+
+/// Visit a sequence, creating the seed of every element from its index
+struct SeqVisitor<F>(F);
+
+impl<'de, F: Fn(usize) -> S, S: DeserializeSeed<'de>> Visitor<'de> for SeqVisitor<F> {
+    type Value = Vec<S::Value>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of messages")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut elements = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(element) = seq.next_element_seed((self.0)(elements.len()))? {
+            elements.push(element);
+        }
+        Ok(elements)
+    }
+}
+
+/// Return the field names of a struct with a derived `Deserialize` implementation
+fn struct_fields<T: DeserializeOwned>() -> &'static [&'static str] {
+    /// Deserializer that only records the field names passed by the struct
+    struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> Deserializer<'de> for FieldNames<'_> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("expected a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(de::Error::custom("field names recorded"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+            option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+            ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldNames(&mut fields));
+    fields
+}
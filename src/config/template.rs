@@ -0,0 +1,222 @@
+//! Placeholders in text messages, replaced when the payload is rendered
+//!
+//! - `{date}` or `{date:%H:%M}`: time of the payload, formatted like `strftime`
+//! - `{env:NAME}`: value of an environment variable
+//! - `{file:path}`: content of a file, `{file:path.json#/pointer}` selects a JSON value
+//! - `{hostname}`: name of this computer
+//!
+//! Literal braces are written as `{{` and `}}`.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use time::{format_description, OffsetDateTime};
+
+/// Replace all placeholders in `text`
+pub(super) fn render(text: &str, timestamp: OffsetDateTime) -> Result<String> {
+    replace(text, false, |placeholder| {
+        render_placeholder(placeholder, timestamp)
+    })
+}
+
+/// Return the paths of the files read by the `{file:...}` placeholders
+pub(super) fn files(text: &str) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    // invalid placeholders are reported when the text is rendered
+    let _ = replace(text, false, |placeholder| {
+        if let Some(argument) = placeholder.strip_prefix("file:") {
+            files.push(file_argument(argument).0.into());
+        }
+        Ok(String::new())
+    });
+    files
+}
+
+/// Resolve the relative paths of the `{file:...}` placeholders against `directory`
+pub(super) fn resolve_files(text: &str, directory: &Path) -> String {
+    let resolved = replace(text, true, |placeholder| {
+        let Some(argument) = placeholder.strip_prefix("file:") else {
+            return Ok(format!("{{{placeholder}}}"));
+        };
+        let (path, pointer) = file_argument(argument);
+        let path = directory.join(path);
+        Ok(match pointer {
+            Some(pointer) => format!("{{file:{}#{pointer}}}", path.display()),
+            None => format!("{{file:{}}}", path.display()),
+        })
+    });
+    // invalid placeholders are reported when the text is rendered
+    resolved.unwrap_or_else(|_| text.to_owned())
+}
+
+/// Replace every placeholder with the result of `f`
+///
+/// Literal braces are kept escaped with `escaped`, e.g. when the output is a template again.
+fn replace(text: &str, escaped: bool, mut f: impl FnMut(&str) -> Result<String>) -> Result<String> {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(['{', '}']) {
+        output.push_str(&rest[..start]);
+        let brace = &rest[start..=start];
+        rest = &rest[start + 1..];
+        if let Some(after) = rest.strip_prefix(brace) {
+            output.push_str(brace);
+            if escaped {
+                output.push_str(brace);
+            }
+            rest = after;
+            continue;
+        }
+        anyhow::ensure!(
+            brace == "{",
+            "unmatched `}}`, use `}}}}` for a literal brace"
+        );
+
+        let end = rest
+            .find('}')
+            .context("unclosed placeholder, use `{{` for a literal brace")?;
+        let placeholder = &rest[..end];
+        output
+            .push_str(&f(placeholder).with_context(|| format!("placeholder `{{{placeholder}}}`"))?);
+        rest = &rest[end + 1..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// Split the argument of `{file:...}` into the path and the optional JSON pointer
+fn file_argument(argument: &str) -> (&str, Option<&str>) {
+    match argument.split_once('#') {
+        Some((path, pointer)) => (path, Some(pointer)),
+        None => (argument, None),
+    }
+}
+
+fn render_placeholder(placeholder: &str, timestamp: OffsetDateTime) -> Result<String> {
+    let (name, argument) = match placeholder.split_once(':') {
+        Some((name, argument)) => (name, Some(argument)),
+        None => (placeholder, None),
+    };
+    Ok(match (name, argument) {
+        ("date", format) => {
+            let format = format_description::parse_strftime_borrowed(format.unwrap_or("%Y-%m-%d"))
+                .context("invalid date format")?;
+            timestamp.format(&format)?
+        }
+        ("env", Some(name)) => {
+            env::var(name).with_context(|| format!("environment variable {name:?}"))?
+        }
+        ("file", Some(argument)) => {
+            let (path, pointer) = file_argument(argument);
+            let content =
+                fs::read_to_string(path).with_context(|| format!("load file: {path:?}"))?;
+            let Some(pointer) = pointer else {
+                return Ok(content.trim().to_owned());
+            };
+            let value: Value =
+                serde_json::from_str(&content).with_context(|| format!("parse json: {path:?}"))?;
+            match value
+                .pointer(pointer)
+                .with_context(|| format!("no value at {pointer:?} in {path:?}"))?
+            {
+                Value::String(string) => string.clone(),
+                value => value.to_string(),
+            }
+        }
+        ("hostname", None) => gethostname::gethostname().to_string_lossy().into_owned(),
+        _ => anyhow::bail!(
+            "unknown placeholder, expected `date`, `env:NAME`, `file:PATH` or `hostname`"
+        ),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, path::Path};
+
+    use time::{Date, Month};
+
+    use super::{files, render, resolve_files};
+
+    #[test]
+    fn render_placeholders() {
+        let timestamp = Date::from_calendar_date(2024, Month::March, 5)
+            .unwrap()
+            .with_hms(14, 7, 9)
+            .unwrap()
+            .assume_utc();
+        let render = |text: &str| render(text, timestamp).map_err(|error| format!("{error:#}"));
+
+        assert_eq!(render("plain text").unwrap(), "plain text");
+        assert_eq!(render("{date:%H:%M} {date}").unwrap(), "14:07 2024-03-05");
+        assert_eq!(render("{{date}} }}").unwrap(), "{date} }");
+        assert_eq!(
+            render("{env:CARGO_PKG_NAME}").unwrap(),
+            env!("CARGO_PKG_NAME")
+        );
+        assert!(render("{file:Cargo.toml#/package}").is_err());
+
+        let dir = std::env::temp_dir().join(format!("badgemagic-template-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let status = dir.join("status.json");
+        std::fs::write(&status, r#"{"temp": 21.5, "room": {"name": "A1"}}"#).unwrap();
+        let status = status.display();
+        assert_eq!(
+            render(&format!(
+                "{{file:{status}#/temp}} °C in {{file:{status}#/room/name}}"
+            ))
+            .unwrap(),
+            "21.5 °C in A1"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!render("{hostname}").unwrap().is_empty());
+        assert_eq!(
+            render("{time}").unwrap_err(),
+            "placeholder `{time}`: unknown placeholder, \
+             expected `date`, `env:NAME`, `file:PATH` or `hostname`"
+        );
+        assert_eq!(
+            render("{date").unwrap_err(),
+            "unclosed placeholder, use `{{` for a literal brace"
+        );
+        assert!(render("}").is_err());
+        assert!(render("{env:BADGEMAGIC_UNSET_VARIABLE}").is_err());
+    }
+
+    #[test]
+    fn list_files() {
+        assert_eq!(
+            files("{file:a.txt} {{file:b.txt}} {date} {file:c.json#/x}"),
+            ["a.txt", "c.json"].map(std::path::PathBuf::from)
+        );
+    }
+
+    #[test]
+    fn resolve_file_paths() {
+        let directory = Path::new("config");
+        let absolute = env::temp_dir().join("b.json");
+        let text = format!(
+            "{{file:a.txt}} {{{{file:b.txt}}}} {{date:%H}} {{file:{}#/x}}",
+            absolute.display()
+        );
+        let resolved = resolve_files(&text, directory);
+        assert_eq!(
+            files(&resolved),
+            [directory.join("a.txt"), absolute.clone()]
+        );
+        assert_eq!(
+            resolved,
+            format!(
+                "{{file:{}}} {{{{file:b.txt}}}} {{date:%H}} {{file:{}#/x}}",
+                directory.join("a.txt").display(),
+                absolute.display()
+            )
+        );
+        assert_eq!(resolve_files("{file:a.txt", directory), "{file:a.txt");
+    }
+}
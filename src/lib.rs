@@ -12,6 +12,15 @@ pub mod ble;
 #[cfg(feature = "embedded-graphics")]
 pub mod util;
 
+#[cfg(all(feature = "serde", feature = "embedded-graphics"))]
+pub mod config;
+
+#[cfg(feature = "serde")]
+pub mod schedule;
+
+#[cfg(feature = "serde")]
+pub mod badge_magic;
+
 #[cfg(feature = "embedded-graphics")]
 pub use embedded_graphics;
 
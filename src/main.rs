@@ -2,29 +2,33 @@
 #![allow(clippy::unnecessary_debug_formatting)]
 #![allow(clippy::too_many_lines)]
 
-use std::{fs, io::BufReader, path::PathBuf};
+use std::{
+    collections::BTreeSet,
+    fmt::Display,
+    fs,
+    io::{self, Read, Write},
+    path::{self, Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
 
 use anyhow::{Context, Result};
 use badgemagic::{
+    badge_magic::SavedBadge,
     ble::Device as BleDevice,
-    protocol::{Brightness, Mode, PayloadBuffer, Speed, Style},
+    config::{self, BadgeConfig, Content, MessageSpec, PartialConfig},
+    protocol::{Brightness, Mode, PayloadBuffer, Speed, State, MAX_PAYLOAD_SIZE},
+    schedule::Playlist,
     usb_hid::Device as UsbDevice,
 };
-use base64::Engine;
-use clap::{Parser, ValueEnum};
-use embedded_graphics::{
-    geometry::Point,
-    image::{Image, ImageRawLE},
-    pixelcolor::BinaryColor,
-    text::Text,
-    Drawable, Pixel,
-};
-use image::{
-    codecs::gif::GifDecoder, imageops::FilterType, AnimationDecoder, ImageReader,
-    Pixel as ImagePixel,
+use clap::{
+    parser::ValueSource, ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand,
+    ValueEnum,
 };
-use serde::Deserialize;
-use u8g2_fonts::{fonts::u8g2_font_lucasfont_alternate_tf, U8g2TextStyle};
+use notify_debouncer_full::{new_debouncer, notify::RecursiveMode, DebouncedEvent};
+use serde::de::DeserializeOwned;
+use time::{OffsetDateTime, UtcOffset};
 
 #[derive(Parser)]
 /// Upload a configuration with up to 8 messages to an LED badge
@@ -41,86 +45,697 @@ use u8g2_fonts::{fonts::u8g2_font_lucasfont_alternate_tf, U8g2TextStyle};
     ",
 )]
 struct Args {
-    /// File format of the config file (toml, json)
-    #[clap(long)]
-    format: Option<String>,
+    #[clap(subcommand)]
+    command: Command,
+}
 
+#[derive(Subcommand)]
+enum Command {
+    /// Upload messages to a badge
+    Send {
+        #[clap(flatten)]
+        device: DeviceArgs,
+
+        #[clap(flatten)]
+        input: InputArgs,
+
+        /// Upload again whenever the config file or a file used by a message changes
+        #[clap(long)]
+        watch: bool,
+    },
+
+    /// List all devices visible to a transport
+    List {
+        /// Transport protocol to search, all transports by default
+        #[clap(long)]
+        transport: Option<TransportProtocol>,
+    },
+
+    /// Show the messages in the terminal instead of uploading them
+    Preview {
+        #[clap(flatten)]
+        input: InputArgs,
+    },
+
+    /// Show the content of a payload file created by `export`
+    Decode {
+        /// Path to the payload file, `-` to read it from stdin
+        payload: PathBuf,
+    },
+
+    /// Check the configuration without uploading it
+    Validate {
+        #[clap(flatten)]
+        input: InputArgs,
+    },
+
+    /// Write the payload that would be uploaded to a file
+    Export {
+        #[clap(flatten)]
+        input: InputArgs,
+
+        /// Path to the output file, `-` to write it to stdout
+        #[clap(long, short)]
+        output: PathBuf,
+
+        /// Format of the output file
+        #[clap(long, default_value = "payload")]
+        output_format: ExportFormat,
+    },
+
+    /// Upload the active entry of a playlist, whenever the active entry or its content changes
+    ///
+    /// The times in the playlist are local times.
+    Schedule {
+        #[clap(flatten)]
+        device: DeviceArgs,
+
+        /// Path to the playlist file
+        playlist: PathBuf,
+    },
+
+    /// Print the JSON Schema of the configuration file format
+    Schema,
+}
+
+/// Select the badge to upload to
+#[derive(clap::Args)]
+#[clap(next_help_heading = "Device options")]
+struct DeviceArgs {
     /// Transport protocol to use
-    #[clap(long)]
+    #[clap(long, default_value = "usb")]
     transport: TransportProtocol,
 
     /// Device name for BLE device discovery
     #[clap(long)]
     device_name: Option<String>,
+}
 
-    /// List all devices visible to a transport and exit
+/// Load the messages from a config file or the command line
+#[derive(Clone, clap::Args)]
+#[clap(next_help_heading = "Config options")]
+struct InputArgs {
+    /// File format of the config file, detected from the file extension by default
     #[clap(long)]
-    list_devices: bool,
+    format: Option<ConfigFormat>,
 
-    /// Path to TOML configuration file
-    #[clap(required_unless_present = "list_devices")]
+    /// Path to the configuration file, `-` to read it from stdin
+    #[clap(required_unless_present = "content")]
     config: Option<PathBuf>,
+
+    #[clap(flatten)]
+    messages: MessageArgs,
 }
 
-#[derive(Clone, Deserialize, ValueEnum)]
-#[serde(rename_all = "kebab-case")]
+impl InputArgs {
+    /// Load the config, `matches` are the matches of the subcommand
+    fn config(self, matches: &ArgMatches) -> Result<BadgeConfig> {
+        let Some((source, format, name)) = self.read()? else {
+            return Ok(self.messages.config(matches));
+        };
+        let mut config: BadgeConfig = match format {
+            ConfigFormat::BadgeMagic => format
+                .parse::<SavedBadge>(&source, &name)
+                .context("parse badge")?
+                .to_config(),
+            _ => format.parse(&source, &name).context("parse config")?,
+        };
+        if let Some(brightness) = self.messages.brightness {
+            config.brightness = brightness;
+        }
+        Ok(config)
+    }
+
+    /// Load the config like [`config`](Self::config), but keep the messages that can't be parsed
+    fn partial_config(self, matches: &ArgMatches) -> Result<PartialConfig> {
+        let Some((source, format, name)) = self.read()? else {
+            return Ok(self.messages.config(matches).into());
+        };
+        let mut config: PartialConfig = match format {
+            ConfigFormat::BadgeMagic => format
+                .parse::<SavedBadge>(&source, &name)
+                .context("parse badge")?
+                .to_config()
+                .into(),
+            _ => format.parse(&source, &name).context("parse config")?,
+        };
+        if let Some(brightness) = self.messages.brightness {
+            config.brightness = brightness;
+        }
+        Ok(config)
+    }
+
+    /// Read the config file, returns its content, format and name
+    fn read(&self) -> Result<Option<(String, ConfigFormat, String)>> {
+        let Some(config_path) = &self.config else {
+            return Ok(None);
+        };
+        let (config, format, name) = if config_path.as_os_str() == "-" {
+            let format = self
+                .format
+                .context("reading the config from stdin requires --format")?;
+            let config = io::read_to_string(io::stdin()).context("read config from stdin")?;
+            (config, format, "<stdin>".into())
+        } else {
+            let format = match self.format {
+                Some(format) => format,
+                None => ConfigFormat::from_extension(config_path)?,
+            };
+            let config = fs::read_to_string(config_path)
+                .with_context(|| format!("load config: {config_path:?}"))?;
+            (config, format, config_path.display().to_string())
+        };
+        Ok(Some((config, format, name)))
+    }
+
+    fn payload(self, matches: &ArgMatches) -> Result<PayloadBuffer> {
+        render(&self.config(matches)?)
+    }
+}
+
+/// Build messages from the command line instead of a config file
+///
+/// Every `--text`, `--image` and `--hex` adds a message. The style options apply to the preceding
+/// message, or to all messages if they are used before the first message.
+#[derive(Clone, clap::Args)]
+#[clap(next_help_heading = "Message options")]
+#[clap(group = clap::ArgGroup::new("content").multiple(true).conflicts_with("config"))]
+struct MessageArgs {
+    /// Add a text message
+    #[clap(long, group = "content")]
+    text: Vec<String>,
+
+    /// Add an image message (PNG, JPEG, SVG, GIF, APNG, WebP, ...)
+    #[clap(long, group = "content")]
+    image: Vec<PathBuf>,
+
+    /// Add a message with pixels as hex string (22 hex digits per group of 8 columns)
+    #[clap(long, group = "content")]
+    hex: Vec<String>,
+
+    /// Display mode (left, right, up, down, center, fast, drop, curtain, laser)
+    #[clap(long, requires = "content")]
+    mode: Vec<Mode>,
+
+    /// Animation speed (0 to 7)
+    #[clap(long, requires = "content", value_parser = parse_speed)]
+    speed: Vec<Speed>,
+
+    /// Let the message blink
+    #[clap(long, requires = "content", action = ArgAction::Count)]
+    blink: u8,
+
+    /// Show a dotted border around the display
+    #[clap(long, requires = "content", action = ArgAction::Count)]
+    border: u8,
+
+    /// Display brightness (0.25, 0.5, 0.75 or 1.0)
+    #[clap(long, value_parser = parse_brightness)]
+    brightness: Option<Brightness>,
+}
+
+impl MessageArgs {
+    /// Build the config from the arguments, in the order they were passed
+    fn config(self, matches: &ArgMatches) -> BadgeConfig {
+        enum Arg {
+            Content(Content),
+            Mode(Mode),
+            Speed(Speed),
+            Blink,
+            Border,
+        }
+
+        let indices = |id: &str| {
+            // flags have a default value, which has an index as well
+            let passed = matches.value_source(id) == Some(ValueSource::CommandLine);
+            matches
+                .indices_of(id)
+                .filter(|_| passed)
+                .into_iter()
+                .flatten()
+        };
+        let mut args: Vec<_> = indices("text")
+            .zip(self.text)
+            .map(|(i, text)| {
+                let content = Content::Text {
+                    text,
+                    fit: false,
+                    wrap: false,
+                    template: false,
+                };
+                (i, Arg::Content(content))
+            })
+            .chain(
+                indices("image")
+                    .zip(self.image)
+                    .map(|(i, path)| (i, Arg::Content(image_content(path)))),
+            )
+            .chain(
+                indices("hex")
+                    .zip(self.hex)
+                    .map(|(i, hex)| (i, Arg::Content(Content::Hex { hex }))),
+            )
+            .chain(indices("mode").zip(self.mode.into_iter().map(Arg::Mode)))
+            .chain(indices("speed").zip(self.speed.into_iter().map(Arg::Speed)))
+            .chain(indices("blink").map(|i| (i, Arg::Blink)))
+            .chain(indices("border").map(|i| (i, Arg::Border)))
+            .collect();
+        args.sort_by_key(|(i, _)| *i);
+
+        let mut defaults = MessageSpec::new(Content::Bitstring {
+            bitstring: String::new(),
+        });
+        let mut messages: Vec<MessageSpec> = Vec::new();
+        for (_, arg) in args {
+            let message = messages.last_mut().unwrap_or(&mut defaults);
+            match arg {
+                Arg::Content(content) => messages.push(MessageSpec {
+                    content,
+                    ..defaults.clone()
+                }),
+                Arg::Mode(mode) => message.mode = mode,
+                Arg::Speed(speed) => message.speed = Some(speed),
+                Arg::Blink => message.blink = true,
+                Arg::Border => message.border = true,
+            }
+        }
+
+        BadgeConfig {
+            brightness: self.brightness.unwrap_or_default(),
+            messages,
+        }
+    }
+}
+
+/// Select the content type from the file extension
+fn image_content(path: PathBuf) -> Content {
+    let (layout, raster) = Default::default();
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("svg") => Content::SvgFile {
+            svg_file: path,
+            raster,
+        },
+        Some("gif") => Content::GifFile {
+            gif_file: path,
+            layout,
+            raster,
+        },
+        Some("apng") => Content::ApngFile {
+            apng_file: path,
+            layout,
+            raster,
+        },
+        Some("webp") => Content::WebpFile {
+            webp_file: path,
+            layout,
+            raster,
+        },
+        _ => Content::ImageFile {
+            img_file: path,
+            layout,
+            raster,
+        },
+    }
+}
+
+fn parse_speed(s: &str) -> Result<Speed> {
+    Ok(Speed::try_from(s.parse::<u8>()?)?)
+}
+
+fn parse_brightness(s: &str) -> Result<Brightness> {
+    let brightness: f32 = s.parse()?;
+    anyhow::ensure!(
+        (0.0..=1.0).contains(&brightness),
+        "brightness must be between 0 and 1"
+    );
+    Ok(brightness.into())
+}
+
+#[derive(Clone, Copy, ValueEnum)]
 enum TransportProtocol {
     Usb,
     Ble,
 }
 
-#[derive(Deserialize)]
-#[serde(deny_unknown_fields)]
-struct Config {
-    #[serde(default)]
-    brightness: Brightness,
-
-    #[serde(rename = "message")]
-    messages: Vec<Message>,
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    /// Raw payload as uploaded to the badge
+    Payload,
+    /// Badge for the Badge Magic app (JSON)
+    BadgeMagic,
+    /// One line per message with its pixels as hex string
+    Hex,
 }
 
-#[derive(Deserialize)]
-struct Message {
-    #[serde(default)]
-    blink: bool,
-
-    #[serde(default)]
-    border: bool,
+#[derive(Clone, Copy, ValueEnum)]
+enum ConfigFormat {
+    Toml,
+    Json,
+    Yaml,
+    Ron,
+    /// Badge saved by the Badge Magic app (JSON)
+    BadgeMagic,
+}
 
-    #[serde(default)]
-    speed: Speed,
+impl ConfigFormat {
+    fn from_extension(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .context("missing file extension for config file, use --format")?;
+        Ok(match extension.to_str().unwrap_or_default() {
+            "toml" => Self::Toml,
+            "json" => Self::Json,
+            "yaml" | "yml" => Self::Yaml,
+            "ron" => Self::Ron,
+            _ => anyhow::bail!("unsupported config file extension: {extension:?}"),
+        })
+    }
 
-    #[serde(default)]
-    mode: Mode,
+    /// Parse the config, errors point to the location in the source
+    fn parse<T: DeserializeOwned>(self, source: &str, name: &str) -> Result<T> {
+        /// Strip the location added by the `Display` impl of the error
+        fn strip_location(message: &str) -> &str {
+            message
+                .rsplit_once(" at line ")
+                .map_or(message, |(message, _)| message)
+        }
 
-    #[serde(flatten)]
-    content: Content,
+        let (message, location) = match self {
+            Self::Toml => match toml::from_str(source) {
+                Ok(config) => return Ok(config),
+                Err(error) => (
+                    error.message().trim().to_owned(),
+                    error.span().map(|span| line_column(source, span.start)),
+                ),
+            },
+            Self::Json | Self::BadgeMagic => match serde_json::from_str(source) {
+                Ok(config) => return Ok(config),
+                Err(error) => (
+                    strip_location(&error.to_string()).to_owned(),
+                    Some((error.line(), error.column())).filter(|&(line, _)| line > 0),
+                ),
+            },
+            Self::Yaml => match serde_yaml_ng::from_str(source) {
+                Ok(config) => return Ok(config),
+                Err(error) => (
+                    strip_location(&error.to_string()).to_owned(),
+                    error
+                        .location()
+                        .map(|location| (location.line(), location.column())),
+                ),
+            },
+            Self::Ron => match ron::Options::default()
+                .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+                .from_str(source)
+            {
+                Ok(config) => return Ok(config),
+                Err(error) => (
+                    error.code.to_string(),
+                    Some((error.span.start.line, error.span.start.col)),
+                ),
+            },
+        };
+
+        let Some((line, column)) = location else {
+            anyhow::bail!("{name}: {message}");
+        };
+        let text = source.lines().nth(line - 1).unwrap_or_default();
+        let marker = format!("{:>width$}", "^", width = column.max(1));
+        anyhow::bail!("{name}:{line}:{column}: {message}\n{line:>4} | {text}\n     | {marker}")
+    }
 }
 
-#[derive(Deserialize)]
-#[serde(deny_unknown_fields, untagged)]
-enum Content {
-    Text { text: String },
-    Bitstring { bitstring: String },
-    BitmapBase64 { width: u32, bitmap_base64: String },
-    BitmapFile { width: u32, bitmap_file: PathBuf },
-    ImageFile { img_file: PathBuf },
-    GifFile { gif_file: PathBuf },
+/// Return the line and column (both starting at 1) of the byte `offset`
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
 }
 
 fn main() -> Result<()> {
-    let mut args = Args::parse();
+    let matches = Args::command().get_matches();
+    let args = Args::from_arg_matches(&matches)?;
+    // the message options are parsed from the matches of the subcommand
+    let matches = matches
+        .subcommand()
+        .map_or(&matches, |(_, matches)| matches);
+
+    match args.command {
+        Command::Send {
+            device,
+            input,
+            watch: false,
+        } => write_payload(&device, input.payload(matches)?),
+        Command::Send {
+            device,
+            input,
+            watch: true,
+        } => watch(&device, &input, matches),
+        Command::List { transport } => match transport {
+            Some(transport) => list_devices(transport),
+            None => TransportProtocol::value_variants()
+                .iter()
+                .try_for_each(|&transport| list_devices(transport)),
+        },
+        Command::Preview { input } => {
+            print_payload(&input.payload(matches)?);
+            Ok(())
+        }
+        Command::Decode { payload } => {
+            let data = if payload.as_os_str() == "-" {
+                let mut data = Vec::new();
+                io::stdin()
+                    .read_to_end(&mut data)
+                    .context("read payload from stdin")?;
+                data
+            } else {
+                fs::read(&payload).with_context(|| format!("load payload: {payload:?}"))?
+            };
+            print_payload(&PayloadBuffer::from_bytes(&data).context("decode payload")?);
+            Ok(())
+        }
+        Command::Validate { input } => validate(&input.partial_config(matches)?),
+        Command::Export {
+            input,
+            output,
+            output_format,
+        } => {
+            let payload = input.payload(matches)?;
+            let data = match output_format {
+                ExportFormat::Payload => payload.into_padded_bytes().as_ref().to_vec(),
+                ExportFormat::BadgeMagic => {
+                    serde_json::to_vec_pretty(&SavedBadge::from_payload(&payload))?
+                }
+                ExportFormat::Hex => payload
+                    .messages()
+                    .map(|message| message.to_hex() + "\n")
+                    .collect::<String>()
+                    .into_bytes(),
+            };
+            if output.as_os_str() == "-" {
+                io::stdout()
+                    .write_all(&data)
+                    .context("write output to stdout")
+            } else {
+                fs::write(&output, data).with_context(|| format!("write output: {output:?}"))
+            }
+        }
+        Command::Schedule { device, playlist } => schedule(&device, &playlist),
+        Command::Schema => {
+            println!("{}", serde_json::to_string_pretty(&config::json_schema())?);
+            Ok(())
+        }
+    }
+}
+
+/// Upload the messages and upload them again whenever one of their files changes
+///
+/// Errors are reported, but don't stop watching.
+fn watch(device: &DeviceArgs, input: &InputArgs, matches: &ArgMatches) -> Result<()> {
+    anyhow::ensure!(
+        input.config.as_deref() != Some(Path::new("-")),
+        "--watch can not read the config from stdin"
+    );
+
+    let (sender, receiver) = mpsc::channel();
+    let mut debouncer =
+        new_debouncer(Duration::from_millis(300), None, sender).context("create file watcher")?;
+    let mut watched = BTreeSet::new();
+    loop {
+        let config = input.clone().config(matches);
+
+        // the config file is watched even if it could not be parsed
+        let mut files: Vec<_> = input.config.iter().cloned().collect();
+        if let Ok(config) = &config {
+            files.extend(config.files());
+        }
+        let files = files
+            .iter()
+            .map(path::absolute)
+            .collect::<io::Result<Vec<_>>>()?;
+
+        // watch the directories, editors often replace files instead of writing them
+        let directories: BTreeSet<_> = files
+            .iter()
+            .filter_map(|file| {
+                if file.is_dir() {
+                    Some(file.clone())
+                } else {
+                    file.parent().map(Path::to_path_buf)
+                }
+            })
+            .collect();
+        for directory in watched.difference(&directories) {
+            let _ = debouncer.unwatch(directory);
+        }
+        for directory in directories.difference(&watched) {
+            if let Err(error) = debouncer.watch(directory, RecursiveMode::NonRecursive) {
+                eprintln!("warning: unable to watch {directory:?}: {error}");
+            }
+        }
+        watched = directories;
+
+        match config.and_then(|config| write_payload(device, render(&config)?)) {
+            Ok(()) => eprintln!("payload uploaded, waiting for changes"),
+            Err(error) => eprintln!("error: {error:#}"),
+        }
 
-    if args.list_devices {
-        return list_devices(&args.transport);
+        loop {
+            match receiver.recv().context("file watcher stopped")? {
+                Ok(events) => {
+                    // reading the files while rendering causes access events
+                    let changed = |event: &DebouncedEvent| {
+                        !event.kind.is_access()
+                            && event
+                                .paths
+                                .iter()
+                                .any(|path| files.iter().any(|file| path.starts_with(file)))
+                    };
+                    if events.iter().any(changed) {
+                        break;
+                    }
+                }
+                Err(errors) => {
+                    for error in errors {
+                        eprintln!("warning: file watcher: {error}");
+                    }
+                }
+            }
+        }
+        eprintln!("change detected, reloading");
+    }
+}
+
+/// Upload the active entry of the playlist whenever the active entry or its content changes
+///
+/// The playlist and the active config are loaded and rendered again every minute.
+/// Errors are logged and the update is retried a minute later.
+fn schedule(device: &DeviceArgs, playlist_path: &Path) -> Result<()> {
+    /// Print a message with the current time
+    fn log(now: OffsetDateTime, message: impl Display) {
+        eprintln!(
+            "[{} {:02}:{:02}:{:02}] {message}",
+            now.date(),
+            now.hour(),
+            now.minute(),
+            now.second()
+        );
     }
 
-    let payload = generate_payload(&mut args)?;
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let mut session = None;
+    let mut uploaded = None;
+    loop {
+        let now = local_now();
+        let result = (|| {
+            let playlist: Playlist = load(playlist_path).context("load playlist")?;
+            let active = playlist.active(now);
+            let rendered = active
+                .map(|index| {
+                    // relative paths are resolved against the directory of the playlist,
+                    // the files of the config against the directory of the config
+                    let path = playlist_path
+                        .parent()
+                        .unwrap_or(Path::new(""))
+                        .join(&playlist.entries[index].config);
+                    let mut config: BadgeConfig = load(&path)?;
+                    config.resolve_paths(path.parent().unwrap_or(Path::new("")));
+                    config.render_at(now)
+                })
+                .transpose()?;
+
+            // the entry is rendered every minute for templates and changed files, it is only
+            // uploaded if the content changed, the timestamp is ignored for the comparison
+            let content = rendered.as_ref().map(|(payload, _)| {
+                let mut payload = payload.clone();
+                payload.set_timestamp(OffsetDateTime::UNIX_EPOCH);
+                payload.as_bytes().to_vec()
+            });
+            if uploaded.as_ref() == Some(&(active, content.clone())) {
+                return Ok(());
+            }
+
+            if let (Some(index), Some((payload, warnings))) = (active, rendered) {
+                let entry = &playlist.entries[index];
+                for warning in warnings {
+                    log(now, format_args!("warning: {warning}"));
+                }
+                match device.transport {
+                    TransportProtocol::Usb => UsbDevice::single()?.write(payload)?,
+                    TransportProtocol::Ble => runtime.block_on(async {
+                        if session.is_none() {
+                            let device = BleDevice::single(device.device_name.as_deref()).await?;
+                            session = Some(device.connect().await?);
+                        }
+                        let result = session.as_ref().unwrap().write(payload).await;
+                        if result.is_err() {
+                            // discover the device again for the next update
+                            session = None;
+                        }
+                        result
+                    })?,
+                }
+                log(
+                    now,
+                    format_args!("entry {index} uploaded: {:?}", entry.config),
+                );
+            } else {
+                log(now, "no active entry, the badge keeps its content");
+            }
+            uploaded = Some((active, content));
+
+            if let Some(next) = playlist.next_change(now) {
+                log(
+                    now,
+                    format_args!("next update at {} {}", next.date(), next.time()),
+                );
+            }
+            anyhow::Ok(())
+        })();
+        if let Err(error) = result {
+            log(now, format_args!("error: {error:#}"));
+        }
+
+        // check again at the start of the next minute
+        thread::sleep(Duration::from_secs(60 - u64::from(now.second())));
+    }
+}
 
-    write_payload(&args.transport, Option::from(&args.device_name), payload)
+/// Load a config or playlist file, the format is detected from the file extension
+fn load<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let format = ConfigFormat::from_extension(path)?;
+    let source = fs::read_to_string(path).with_context(|| format!("load config: {path:?}"))?;
+    format
+        .parse(&source, &path.display().to_string())
+        .with_context(|| format!("parse config: {path:?}"))
 }
 
-fn list_devices(transport: &TransportProtocol) -> Result<()> {
+fn list_devices(transport: TransportProtocol) -> Result<()> {
     let devices = match transport {
         TransportProtocol::Usb => UsbDevice::list_all(),
         TransportProtocol::Ble => tokio::runtime::Builder::new_current_thread()
@@ -141,178 +756,109 @@ fn list_devices(transport: &TransportProtocol) -> Result<()> {
     Ok(())
 }
 
-fn generate_payload(args: &mut Args) -> Result<PayloadBuffer> {
-    const DISPLAY_HEIGHT: u32 = 11;
-    const DISPLAY_WIDTH: u32 = 44;
-
-    let config_path = args.config.take().unwrap_or_default();
-    let config = fs::read_to_string(&config_path)
-        .with_context(|| format!("load config: {config_path:?}"))?;
-    let config: Config = {
-        let extension = args
-            .format
-            .as_deref()
-            .map(AsRef::as_ref)
-            .or(config_path.extension())
-            .context("missing file extension for config file")?;
-        match extension.to_str().unwrap_or_default() {
-            "json" => serde_json::from_str(&config).context("parse config")?,
-            "toml" => toml::from_str(&config).context("parse config")?,
-            _ => anyhow::bail!("unsupported config file extension: {extension:?}"),
-        }
-    };
-
-    let mut payload = PayloadBuffer::new();
-    payload.set_brightness(config.brightness);
+/// Print all problems of the config and the size of the messages
+fn validate(config: &PartialConfig) -> Result<()> {
+    let validation = config.validate();
+    for diagnostic in &validation.diagnostics {
+        eprintln!("{}: {diagnostic}", diagnostic.severity);
+    }
 
-    for message in config.messages {
-        let mut style = Style::default();
-        if message.blink {
-            style = style.blink();
+    for (index, (message, columns)) in config.messages.iter().zip(&validation.columns).enumerate() {
+        let field = message
+            .as_ref()
+            .map(|message| format!(" ({})", message.content.field()))
+            .unwrap_or_default();
+        match columns {
+            Some(columns) => println!("message {index}{field}: {columns} columns"),
+            None => println!("message {index}{field}: invalid"),
         }
-        if message.border {
-            style = style.border();
-        }
-        style = style.speed(message.speed).mode(message.mode);
-        match message.content {
-            Content::Text { text } => {
-                let text = Text::new(
-                    &text,
-                    Point::new(0, 8),
-                    U8g2TextStyle::new(u8g2_font_lucasfont_alternate_tf, BinaryColor::On),
-                );
-                payload.add_message_drawable(style, &text);
-            }
-            Content::Bitstring { bitstring } => {
-                let lines: Vec<_> = bitstring.trim().lines().collect();
-
-                anyhow::ensure!(
-                    lines.len() == DISPLAY_HEIGHT as usize,
-                    "expected {} lines in bitstring, found {} lines",
-                    DISPLAY_HEIGHT,
-                    lines.len()
-                );
-                let width = lines[0].len();
-                if lines.iter().any(|l| l.len() != width) {
-                    anyhow::bail!(
-                        "lines should have the same length, got: {:?}",
-                        lines.iter().map(|l| l.len()).collect::<Vec<_>>()
-                    );
-                }
-                let mut buffer = payload.add_message(style, width.div_ceil(8));
-
-                for (y, line) in lines.iter().enumerate() {
-                    for (x, c) in line.chars().enumerate() {
-                        match c {
-                            '_' => {
-                                // off
-                            }
-                            'X' => {
-                                Pixel(
-                                    Point::new(x.try_into().unwrap(), y.try_into().unwrap()),
-                                    BinaryColor::On,
-                                )
-                                .draw(&mut buffer)
-                                .unwrap();
-                            }
-                            _ => anyhow::bail!("invalid bit value for bit ({x}, {y}): {c:?}"),
-                        }
-                    }
-                }
-            }
-            Content::BitmapBase64 {
-                width,
-                bitmap_base64: bitmap,
-            } => {
-                let data = if bitmap.ends_with('=') {
-                    base64::engine::general_purpose::STANDARD
-                } else {
-                    base64::engine::general_purpose::STANDARD_NO_PAD
-                }
-                .decode(bitmap)
-                .context("decode bitmap")?;
-                let image_raw = ImageRawLE::<BinaryColor>::new(&data, width);
-                let image = Image::new(&image_raw, Point::zero());
-                payload.add_message_drawable(style, &image);
-            }
-            Content::BitmapFile { width, bitmap_file } => {
-                let data = fs::read(bitmap_file).context("load bitmap")?;
-                let image_raw = ImageRawLE::<BinaryColor>::new(&data, width);
-                let image = Image::new(&image_raw, Point::zero());
-                payload.add_message_drawable(style, &image);
-            }
-            Content::ImageFile { img_file } => {
-                let img_reader = ImageReader::open(img_file)?;
-                let img = img_reader
-                    .decode()?
-                    .resize(u32::MAX, DISPLAY_HEIGHT, FilterType::Nearest)
-                    .into_luma8();
-                let (width, height) = img.dimensions();
-                let mut buffer = payload.add_message(style, width.div_ceil(8) as usize);
-                for y in 0..height {
-                    for x in 0..width {
-                        if img.get_pixel(x, y).0 > [31] {
-                            Pixel(Point::new(x.try_into()?, y.try_into()?), BinaryColor::On)
-                                .draw(&mut buffer)?;
-                        }
-                    }
-                }
-            }
-            Content::GifFile { gif_file } => {
-                let file_in = BufReader::new(fs::File::open(gif_file)?);
-                let frames = GifDecoder::new(file_in)?
-                    .into_frames()
-                    .collect_frames()
-                    .expect("error decoding gif");
-
-                let frame_count = frames.len();
-                let (width, height) = frames.first().unwrap().buffer().dimensions();
-                if height != DISPLAY_HEIGHT || width != DISPLAY_WIDTH {
-                    anyhow::bail!("Expected {DISPLAY_WIDTH}x{DISPLAY_HEIGHT} pixel gif file");
-                }
+    }
+    println!(
+        "payload: {} of {MAX_PAYLOAD_SIZE} bytes",
+        validation.payload_size
+    );
 
-                let mut buffer = payload.add_message(
-                    style,
-                    ((DISPLAY_WIDTH as usize + 4) * frame_count).div_ceil(8),
-                );
+    anyhow::ensure!(validation.is_valid(), "config is invalid");
+    eprintln!("config is valid");
+    Ok(())
+}
 
-                for (i, frame) in frames.iter().enumerate() {
-                    let buf = frame.buffer();
-                    for y in 0..DISPLAY_HEIGHT {
-                        for x in 0..DISPLAY_WIDTH {
-                            if buf.get_pixel(x, y).to_luma().0 > [31] {
-                                Pixel(
-                                    Point::new(
-                                        (x as usize + i * (DISPLAY_WIDTH as usize + 4))
-                                            .try_into()?,
-                                        y.try_into()?,
-                                    ),
-                                    BinaryColor::On,
-                                )
-                                .draw(&mut buffer)?;
-                            }
-                        }
-                    }
-                }
+/// Print the style and the pixels of all messages
+fn print_payload(payload: &PayloadBuffer) {
+    println!("brightness: {}", f32::from(payload.brightness()));
+    for (index, message) in payload.messages().enumerate() {
+        let style = message.style();
+        let mut flags = String::new();
+        for (enabled, flag) in [(style.is_blink(), " blink"), (style.is_border(), " border")] {
+            if enabled {
+                flags.push_str(flag);
             }
         }
+        println!(
+            "\nmessage {index}: mode={} speed={}{flags}, {} columns",
+            style.mode().name(),
+            u8::from(style.speed()),
+            message.width(),
+        );
+        for y in 0..11 {
+            let row: String = (0..message.width())
+                .map(|x| match message.get((x, y)) {
+                    Some(State::On) => '█',
+                    _ => '·',
+                })
+                .collect();
+            println!("{row}");
+        }
     }
+}
 
+/// Render the payload with the current local time
+fn render(config: &BadgeConfig) -> Result<PayloadBuffer> {
+    let (payload, warnings) = config.render_at(local_now())?;
+    for warning in warnings {
+        eprintln!("warning: {warning}");
+    }
     Ok(payload)
 }
 
-fn write_payload(
-    transport: &TransportProtocol,
-    device_name: Option<&String>,
-    payload: PayloadBuffer,
-) -> Result<(), anyhow::Error> {
-    match transport {
+/// Return the current time in the local time zone
+///
+/// The offset is looked up in the time zone database for the current time, so it follows daylight
+/// saving time. Unlike [`UtcOffset::current_local_offset`], which is only the fallback (e.g. on
+/// Windows), this also works in multithreaded processes.
+fn local_now() -> OffsetDateTime {
+    let now = OffsetDateTime::now_utc();
+    let offset = tz_offset(now)
+        .or_else(|| UtcOffset::current_local_offset().ok())
+        .unwrap_or(UtcOffset::UTC);
+    now.to_offset(offset)
+}
+
+/// Return the offset at `now` of the time zone in `TZ` or `/etc/localtime`
+#[cfg(unix)]
+fn tz_offset(now: OffsetDateTime) -> Option<UtcOffset> {
+    let zone = match std::env::var("TZ") {
+        Ok(tz) if !tz.is_empty() => tz::TimeZone::from_posix_tz(tz.trim_start_matches(':')),
+        _ => tz::TimeZone::local(),
+    }
+    .ok()?;
+    let local = zone.find_local_time_type(now.unix_timestamp()).ok()?;
+    UtcOffset::from_whole_seconds(local.ut_offset()).ok()
+}
+
+#[cfg(not(unix))]
+fn tz_offset(_now: OffsetDateTime) -> Option<UtcOffset> {
+    None
+}
+
+fn write_payload(device: &DeviceArgs, payload: PayloadBuffer) -> Result<()> {
+    match device.transport {
         TransportProtocol::Usb => UsbDevice::single()?.write(payload),
         TransportProtocol::Ble => tokio::runtime::Builder::new_current_thread()
             .enable_all()
             .build()?
             .block_on(async {
-                BleDevice::single(device_name.map(String::as_str))
+                BleDevice::single(device.device_name.as_deref())
                     .await?
                     .write(payload)
                     .await
@@ -2,14 +2,22 @@
 
 #[cfg(feature = "embedded-graphics")]
 use embedded_graphics::{
-    draw_target::DrawTarget,
-    geometry::{Dimensions, Point, Size},
+    draw_target::{DrawTarget, DrawTargetExt},
+    geometry::{Dimensions, OriginDimensions, Point, Size},
+    image::{GetPixel, ImageDrawable},
     pixelcolor::BinaryColor,
     prelude::Pixel,
-    primitives::Rectangle,
+    primitives::{PointsIter, Rectangle},
     Drawable,
 };
-use std::num::TryFromIntError;
+#[cfg(feature = "schema")]
+use std::borrow::Cow;
+use std::{
+    fmt::Write,
+    num::TryFromIntError,
+    ops::{BitXor, Range},
+    str::FromStr,
+};
 use time::OffsetDateTime;
 use zerocopy::{BigEndian, FromBytes, Immutable, IntoBytes, KnownLayout, U16};
 
@@ -17,11 +25,12 @@ use zerocopy::{BigEndian, FromBytes, Immutable, IntoBytes, KnownLayout, U16};
 /// ```
 /// use badgemagic::protocol::{Mode, Style};
 /// # (
-/// Style::default().blink().border().mode(Mode::Center)
+/// Style::default().blink().border().with_mode(Mode::Center)
 /// # );
 /// ```
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
 #[must_use]
 pub struct Style {
@@ -36,6 +45,9 @@ pub struct Style {
 
     #[cfg_attr(feature = "serde", serde(default))]
     mode: Mode,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    invert: bool,
 }
 
 impl Style {
@@ -71,10 +83,10 @@ impl Style {
     /// ```
     /// use badgemagic::protocol::{Speed, Style};
     /// # (
-    /// Style::default().speed(Speed::Fps1_2)
+    /// Style::default().with_speed(Speed::Fps1_2)
     /// # );
     /// ```
-    pub fn speed(mut self, speed: Speed) -> Self {
+    pub fn with_speed(mut self, speed: Speed) -> Self {
         self.speed = speed;
         self
     }
@@ -83,7 +95,7 @@ impl Style {
     /// ```
     /// use badgemagic::protocol::{Mode, Style};
     /// # (
-    /// Style::default().mode(Mode::Curtain)
+    /// Style::default().with_mode(Mode::Curtain)
     /// # );
     /// ```
     ///
@@ -91,13 +103,59 @@ impl Style {
     /// ```
     /// use badgemagic::protocol::{Mode, Style};
     /// # (
-    /// Style::default().mode(Mode::Center)
+    /// Style::default().with_mode(Mode::Center)
     /// # );
     /// ```
-    pub fn mode(mut self, mode: Mode) -> Self {
+    pub fn with_mode(mut self, mode: Mode) -> Self {
         self.mode = mode;
         self
     }
+
+    /// Show the message inverted (light background, dark content)
+    ///
+    /// The badge does not support this natively, the pixels are inverted
+    /// when the message is added to the [`PayloadBuffer`].
+    /// This includes the padding columns at the end of the message.
+    /// ```
+    /// use badgemagic::protocol::Style;
+    /// # (
+    /// Style::default().invert()
+    /// # );
+    /// ```
+    pub fn invert(mut self) -> Self {
+        self.invert = true;
+        self
+    }
+
+    /// Return whether the message blinks
+    #[must_use]
+    pub fn is_blink(&self) -> bool {
+        self.blink
+    }
+
+    /// Return whether a border is shown around the display
+    #[must_use]
+    pub fn is_border(&self) -> bool {
+        self.border
+    }
+
+    /// Return whether the message is inverted
+    #[must_use]
+    pub fn is_invert(&self) -> bool {
+        self.invert
+    }
+
+    /// Return the update speed of the animations
+    #[must_use]
+    pub fn speed(&self) -> Speed {
+        self.speed
+    }
+
+    /// Return the display animation
+    #[must_use]
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
 }
 
 /// Animation update speed
@@ -131,6 +189,52 @@ pub enum Speed {
     Fps15,
 }
 
+impl Speed {
+    /// All speeds, from the slowest to the fastest
+    pub const ALL: [Self; 8] = [
+        Self::Fps1_2,
+        Self::Fps1_3,
+        Self::Fps2,
+        Self::Fps2_4,
+        Self::Fps2_8,
+        Self::Fps4_5,
+        Self::Fps7_5,
+        Self::Fps15,
+    ];
+
+    /// Frame rate of the animation in frames per second
+    #[must_use]
+    pub fn frames_per_second(self) -> f32 {
+        match self {
+            Self::Fps1_2 => 1.2,
+            Self::Fps1_3 => 1.3,
+            Self::Fps2 => 2.0,
+            Self::Fps2_4 => 2.4,
+            Self::Fps2_8 => 2.8,
+            Self::Fps4_5 => 4.5,
+            Self::Fps7_5 => 7.5,
+            Self::Fps15 => 15.0,
+        }
+    }
+
+    /// Return the speed with the frame rate closest to `fps`
+    /// ```
+    /// use badgemagic::protocol::Speed;
+    ///
+    /// assert_eq!(Speed::nearest(10.0), Speed::Fps7_5);
+    /// assert_eq!(Speed::nearest(100.0), Speed::Fps15);
+    /// ```
+    #[must_use]
+    pub fn nearest(fps: f32) -> Self {
+        // compare the ratio, not the difference: 1.2 vs 1.3 FPS is a bigger step than 7.5 vs 8 FPS
+        let distance = |speed: &Self| (fps / speed.frames_per_second()).ln().abs();
+        Self::ALL
+            .into_iter()
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+            .unwrap_or_default()
+    }
+}
+
 impl From<Speed> for u8 {
     fn from(value: Speed) -> Self {
         value as u8
@@ -155,9 +259,26 @@ impl TryFrom<u8> for Speed {
     }
 }
 
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for Speed {
+    fn schema_name() -> Cow<'static, str> {
+        "Speed".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "description": "Animation update speed, from 0 (1.2 FPS) to 7 (15 FPS)",
+            "type": "integer",
+            "minimum": 0,
+            "maximum": 7,
+        })
+    }
+}
+
 /// Message display mode
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Mode {
     /// Scroll through the message from left to right
@@ -192,10 +313,86 @@ pub enum Mode {
     Laser,
 }
 
+impl Mode {
+    /// All display modes
+    pub const ALL: [Self; 9] = [
+        Self::Left,
+        Self::Right,
+        Self::Up,
+        Self::Down,
+        Self::Center,
+        Self::Fast,
+        Self::Drop,
+        Self::Curtain,
+        Self::Laser,
+    ];
+
+    /// Name of the mode, as used in configuration files
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Left => "left",
+            Self::Right => "right",
+            Self::Up => "up",
+            Self::Down => "down",
+            Self::Center => "center",
+            Self::Fast => "fast",
+            Self::Drop => "drop",
+            Self::Curtain => "curtain",
+            Self::Laser => "laser",
+        }
+    }
+}
+
+impl FromStr for Mode {
+    type Err = anyhow::Error;
+
+    /// Parse the [name](Self::name) of a mode
+    /// ```
+    /// use badgemagic::protocol::Mode;
+    ///
+    /// assert_eq!("center".parse::<Mode>().unwrap(), Mode::Center);
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::ALL.iter().map(|mode| mode.name()).collect();
+                anyhow::anyhow!("unknown mode {s:?}, expected one of: {}", names.join(", "))
+            })
+    }
+}
+
+impl From<Mode> for u8 {
+    fn from(value: Mode) -> Self {
+        value as u8
+    }
+}
+
+impl TryFrom<u8> for Mode {
+    type Error = TryFromIntError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => Self::Left,
+            1 => Self::Right,
+            2 => Self::Up,
+            3 => Self::Down,
+            4 => Self::Center,
+            5 => Self::Fast,
+            6 => Self::Drop,
+            7 => Self::Curtain,
+            8 => Self::Laser,
+            _ => return Err(u8::try_from(-1).unwrap_err()),
+        })
+    }
+}
+
 /// Display Brightness
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[cfg_attr(feature = "serde", serde(try_from = "f32", into = "u8"))]
+#[cfg_attr(feature = "serde", serde(try_from = "f32", into = "f32"))]
 pub enum Brightness {
     #[default]
     Full = 0x00,
@@ -248,6 +445,27 @@ impl From<f32> for Brightness {
     }
 }
 
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for Brightness {
+    fn schema_name() -> Cow<'static, str> {
+        "Brightness".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "description": "Display brightness, rounded to 0.25, 0.5, 0.75 or 1.0",
+            "type": "number",
+            "minimum": 0,
+            "maximum": 1,
+        })
+    }
+}
+
+/// Maximum size of a padded payload in bytes
+///
+/// The device will brick itself if the payload is too long.
+pub const MAX_PAYLOAD_SIZE: usize = 8192;
+
 const MSG_PADDING_ALIGN: usize = 64;
 
 const MAGIC: [u8; 5] = *b"wang\0";
@@ -289,10 +507,6 @@ impl Timestamp {
             second: ts.second(),
         }
     }
-
-    fn now() -> Self {
-        Self::new(OffsetDateTime::now_utc())
-    }
 }
 
 /// Buffer to create a payload
@@ -320,8 +534,11 @@ impl Timestamp {
 /// # #[cfg(not(feature = "embedded-graphics"))]
 /// # fn main() {}
 /// ```
+#[derive(Debug, Clone)]
 pub struct PayloadBuffer {
     num_messages: u8,
+    /// Bitmask of the inverted messages, the header has no field for it
+    invert: u8,
     data: Vec<u8>,
 }
 
@@ -335,8 +552,15 @@ impl PayloadBuffer {
     /// Create a new empty buffer
     #[must_use]
     pub fn new() -> Self {
+        Self::with_timestamp(OffsetDateTime::now_utc())
+    }
+
+    /// Create a new empty buffer with the given creation time in the header
+    #[must_use]
+    pub fn with_timestamp(timestamp: OffsetDateTime) -> Self {
         Self {
             num_messages: 0,
+            invert: 0,
             data: Header {
                 magic: MAGIC,
                 brightness: Brightness::Full.into(),
@@ -345,7 +569,7 @@ impl PayloadBuffer {
                 speed_and_mode: [0; 8],
                 message_length: [0.into(); 8],
                 _padding_1: [0; 6],
-                timestamp: Timestamp::now(),
+                timestamp: Timestamp::new(timestamp),
                 _padding_2: [0; 20],
             }
             .as_bytes()
@@ -353,19 +577,192 @@ impl PayloadBuffer {
         }
     }
 
+    /// Load a payload as created by [`as_bytes`](Self::as_bytes)
+    /// or [`into_padded_bytes`](Self::into_padded_bytes)
+    ///
+    /// The header does not store whether a message is inverted,
+    /// all messages of the loaded payload have an uninverted style.
+    ///
+    /// The header has no message count either, a slot is loaded as message if it or a later
+    /// slot has a length or a style. Trailing messages without columns are only kept if their
+    /// style differs from speed 0 in [`Mode::Left`] without blink and border.
+    pub fn from_bytes(data: &[u8]) -> anyhow::Result<Self> {
+        let (header, _) = Header::ref_from_prefix(data)
+            .map_err(|_| anyhow::anyhow!("payload too short for the header"))?;
+        anyhow::ensure!(header.magic == MAGIC, "invalid payload magic");
+
+        let is_used = |index: usize| {
+            header.message_length[index].get() != 0
+                || header.speed_and_mode[index] != 0
+                || (header.blink | header.border) & (1 << index) != 0
+        };
+        let num_messages = (0..8).rposition(is_used).map_or(0, |index| index + 1);
+        let len = size_of::<Header>()
+            + header
+                .message_length
+                .iter()
+                .map(|length| usize::from(length.get()) * 11)
+                .sum::<usize>();
+        anyhow::ensure!(
+            data.len() >= len,
+            "payload truncated: expected {len} bytes, got {}",
+            data.len(),
+        );
+        anyhow::ensure!(
+            data[len..].iter().all(|&byte| byte == 0),
+            "unexpected data after the last message",
+        );
+
+        #[allow(clippy::cast_possible_truncation)] // at most 8 messages
+        Ok(Self {
+            num_messages: num_messages as u8,
+            invert: 0,
+            data: data[..len].into(),
+        })
+    }
+
+    fn header(&self) -> &Header {
+        Header::ref_from_prefix(&self.data).unwrap().0
+    }
+
     fn header_mut(&mut self) -> &mut Header {
         Header::mut_from_prefix(&mut self.data).unwrap().0
     }
 
+    /// Decode the style of the message at `index` from the header
+    fn style(&self, index: usize) -> Style {
+        let header = self.header();
+        let speed_and_mode = header.speed_and_mode[index];
+        Style {
+            blink: header.blink & (1 << index) != 0,
+            border: header.border & (1 << index) != 0,
+            speed: Speed::try_from(speed_and_mode >> 4).unwrap_or_default(),
+            mode: Mode::try_from(speed_and_mode & 0x0f).unwrap_or_default(),
+            invert: self.invert & (1 << index) != 0,
+        }
+    }
+
+    /// Encode the style of the message at `index` into the header
+    fn set_style(&mut self, index: usize, style: Style) {
+        fn set_bit(mask: &mut u8, index: usize, value: bool) {
+            if value {
+                *mask |= 1 << index;
+            } else {
+                *mask &= !(1 << index);
+            }
+        }
+
+        set_bit(&mut self.invert, index, style.invert);
+        let header = self.header_mut();
+        set_bit(&mut header.blink, index, style.blink);
+        set_bit(&mut header.border, index, style.border);
+        header.speed_and_mode[index] = ((style.speed as u8) << 4) | style.mode as u8;
+    }
+
+    /// Return the range of the message at `index` in `data`
+    fn message_range(&self, index: usize) -> Range<usize> {
+        let lengths = &self.header().message_length;
+        let start = size_of::<Header>()
+            + lengths[..index]
+                .iter()
+                .map(|length| usize::from(length.get()) * 11)
+                .sum::<usize>();
+        start..start + usize::from(lengths[index].get()) * 11
+    }
+
+    fn message_buffer(&mut self, index: usize) -> MessageBuffer<'_> {
+        let range = self.message_range(index);
+        let width = range.len() / 11 * 8;
+        MessageBuffer {
+            data: FromBytes::mut_from_bytes(&mut self.data[range]).unwrap(),
+            invert: self.invert & (1 << index) != 0,
+            width,
+        }
+    }
+
+    /// Remove all messages and return their style and raw data
+    fn take_messages(&mut self) -> Vec<(Style, Vec<u8>)> {
+        let messages = (0..self.num_messages())
+            .map(|index| {
+                (
+                    self.style(index),
+                    self.data[self.message_range(index)].to_vec(),
+                )
+            })
+            .collect();
+
+        self.data.truncate(size_of::<Header>());
+        self.num_messages = 0;
+        self.invert = 0;
+        let header = self.header_mut();
+        header.blink = 0;
+        header.border = 0;
+        header.speed_and_mode = [0; 8];
+        header.message_length = [0.into(); 8];
+        messages
+    }
+
+    /// Add messages with raw data, as returned by `take_messages`
+    fn put_messages(&mut self, messages: Vec<(Style, Vec<u8>)>) {
+        for (style, data) in messages {
+            let message = self.add_message(style, data.len() / 11);
+            message.data.as_flattened_mut().copy_from_slice(&data);
+        }
+    }
+
+    fn assert_index(&self, index: usize) {
+        let count = self.num_messages;
+        assert!(
+            index < count as usize,
+            "message index out of bounds: the index is {index} but there are {count} messages",
+        );
+    }
+
     pub fn set_brightness(&mut self, brightness: Brightness) {
         self.header_mut().brightness = brightness.into();
     }
 
+    /// Replace the creation time in the header
+    pub fn set_timestamp(&mut self, timestamp: OffsetDateTime) {
+        self.header_mut().timestamp = Timestamp::new(timestamp);
+    }
+
+    /// Return the display brightness
+    #[must_use]
+    pub fn brightness(&self) -> Brightness {
+        Brightness::try_from(self.header().brightness).unwrap_or_default()
+    }
+
     /// Return the current number of messages
-    pub fn num_messages(&mut self) -> usize {
+    #[must_use]
+    pub fn num_messages(&self) -> usize {
         self.num_messages as usize
     }
 
+    /// Iterate over the messages in the buffer
+    /// ```
+    /// use badgemagic::protocol::{Mode, PayloadBuffer, State, Style};
+    ///
+    /// let mut buffer = PayloadBuffer::new();
+    /// buffer
+    ///     .add_message(Style::default().with_mode(Mode::Center), 2)
+    ///     .set((3, 4), State::On);
+    ///
+    /// for message in buffer.messages() {
+    ///     assert_eq!(message.style().mode(), Mode::Center);
+    ///     assert_eq!(message.width(), 16);
+    ///     assert_eq!(message.get((3, 4)), Some(State::On));
+    /// }
+    /// ```
+    #[allow(clippy::missing_panics_doc)] // should never panic
+    #[must_use]
+    pub fn messages(&self) -> impl ExactSizeIterator<Item = MessageView<'_>> + '_ {
+        (0..self.num_messages()).map(|index| MessageView {
+            style: self.style(index),
+            data: FromBytes::ref_from_bytes(&self.data[self.message_range(index)]).unwrap(),
+        })
+    }
+
     /// Add a messages containing the specified `content`
     ///
     /// ## Panics
@@ -407,20 +804,106 @@ impl PayloadBuffer {
         );
         self.num_messages += 1;
 
-        let header = self.header_mut();
+        self.set_style(index, style);
+        self.header_mut().message_length[index] = count.try_into().unwrap();
 
-        if style.blink {
-            header.blink |= 1 << index;
+        let start = self.data.len();
+        let background = if style.invert { 0xff } else { 0x00 };
+        self.data.resize(start + count * 11, background);
+        MessageBuffer {
+            data: FromBytes::mut_from_bytes(&mut self.data[start..]).unwrap(),
+            invert: style.invert,
+            width: count * 8,
         }
-        if style.border {
-            header.border |= 1 << index;
+    }
+
+    /// Add a message containing a copy of `bitmap`
+    ///
+    /// ## Panics
+    /// Panics if the supported number of messages is reached.
+    pub fn add_message_bitmap(&mut self, style: Style, bitmap: &Bitmap) -> MessageBuffer<'_> {
+        let mut message = self.add_message(style, bitmap.data.len());
+        message.load(bitmap);
+        message
+    }
+
+    /// Add a message with pixels loaded from a hex string, see [`Bitmap::from_hex`]
+    ///
+    /// ## Panics
+    /// Panics if the supported number of messages is reached.
+    pub fn add_message_hex(
+        &mut self,
+        style: Style,
+        hex: &str,
+    ) -> anyhow::Result<MessageBuffer<'_>> {
+        Ok(self.add_message_bitmap(style, &Bitmap::from_hex(hex)?))
+    }
+
+    /// Remove the message at `index`, the following messages are moved up
+    ///
+    /// ## Panics
+    /// Panics if there is no message at `index`.
+    pub fn remove_message(&mut self, index: usize) {
+        self.assert_index(index);
+        let mut messages = self.take_messages();
+        let _ = messages.remove(index);
+        self.put_messages(messages);
+    }
+
+    /// Move the message at index `from` to index `to`
+    ///
+    /// The messages in between are shifted to fill the gap.
+    ///
+    /// ## Panics
+    /// Panics if there is no message at `from` or `to`.
+    pub fn move_message(&mut self, from: usize, to: usize) {
+        self.assert_index(from);
+        self.assert_index(to);
+        let mut messages = self.take_messages();
+        let message = messages.remove(from);
+        messages.insert(to, message);
+        self.put_messages(messages);
+    }
+
+    /// Change the style of the message at `index`, the content is kept
+    ///
+    /// ## Panics
+    /// Panics if there is no message at `index`.
+    pub fn set_message_style(&mut self, index: usize, style: Style) {
+        self.assert_index(index);
+        let previous = self.style(index);
+        self.set_style(index, style);
+        if previous.invert != style.invert {
+            // the content is stored inverted, restore it before it is shown normally and vice versa
+            self.message_buffer(index).invert();
         }
-        header.speed_and_mode[index] = ((style.speed as u8) << 4) | style.mode as u8;
-        header.message_length[index] = count.try_into().unwrap();
+    }
 
-        let start = self.data.len();
-        self.data.resize(start + count * 11, 0);
-        MessageBuffer(FromBytes::mut_from_bytes(&mut self.data[start..]).unwrap())
+    /// Replace the content of the message at `index` with `count * 8` empty columns
+    ///
+    /// The style of the message is kept.
+    ///
+    /// ## Panics
+    /// Panics if there is no message at `index`.
+    pub fn replace_message(&mut self, index: usize, count: usize) -> MessageBuffer<'_> {
+        self.assert_index(index);
+        let mut messages = self.take_messages();
+        let (style, data) = &mut messages[index];
+        *data = vec![if style.invert { 0xff } else { 0x00 }; count * 11];
+        self.put_messages(messages);
+        self.message_buffer(index)
+    }
+
+    /// Replace the content of the message at `index` with a copy of `bitmap`
+    ///
+    /// The style of the message is kept.
+    ///
+    /// ## Panics
+    /// Panics if there is no message at `index`.
+    pub fn replace_message_bitmap(&mut self, index: usize, bitmap: &Bitmap) -> MessageBuffer<'_> {
+        let mut message = self.replace_message(index, bitmap.data.len());
+        message.load(bitmap);
+        message
     }
 
     /// Get the current payload as bytes (without padding)
@@ -454,16 +937,44 @@ impl PayloadBuffer {
 /// A display buffer for a single message.
 ///
 /// Can be used as an `embedded_graphics::DrawTarget`.
-pub struct MessageBuffer<'a>(&'a mut [[u8; 11]]);
+///
+/// If the message [style](Style::invert) is inverted, all pixel
+/// operations work on the content before the inversion.
+pub struct MessageBuffer<'a> {
+    data: &'a mut [[u8; 11]],
+    invert: bool,
+    /// Number of columns, less than `data.len() * 8` for a [`Bitmap`]
+    width: usize,
+}
 
 impl MessageBuffer<'_> {
+    /// Return the width of the message in pixels
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Get the state of the pixel at point (`x`, `y`)
+    ///
+    /// Returns `None` if the pixel is out of bounds.
+    #[must_use]
+    pub fn get(&self, (x, y): (usize, usize)) -> Option<State> {
+        if x >= self.width {
+            return None;
+        }
+        get_pixel(self.data, (x, y)).map(|state| state ^ self.invert)
+    }
+
     /// Set the state of the pixel at point (`x`, `y`)
     ///
     /// Returns `None` if the pixel was out of bounds.
     pub fn set(&mut self, (x, y): (usize, usize), state: State) -> Option<()> {
-        let byte = self.0.get_mut(x / 8)?.get_mut(y)?;
+        if x >= self.width {
+            return None;
+        }
+        let byte = self.data.get_mut(x / 8)?.get_mut(y)?;
         let bit = 0x80 >> (x % 8);
-        match state {
+        match state ^ self.invert {
             State::Off => {
                 *byte &= !bit;
             }
@@ -474,6 +985,134 @@ impl MessageBuffer<'_> {
         Some(())
     }
 
+    /// Copy the content of the message into a new [`Bitmap`]
+    pub fn to_bitmap(&self) -> Bitmap {
+        Bitmap::from_message_data(self.data, self.width, self.invert)
+    }
+
+    /// Encode the content of the message as hex string, see [`Bitmap::from_hex`]
+    #[must_use]
+    pub fn to_hex(&self) -> String {
+        encode_hex(self.data, self.invert)
+    }
+
+    /// Overwrite the content with `bitmap`, both must have the same number of columns
+    fn load(&mut self, bitmap: &Bitmap) {
+        self.data.copy_from_slice(&bitmap.data);
+        if self.invert {
+            self.invert();
+        }
+    }
+
+    /// Set all pixels to `state`
+    pub fn fill(&mut self, state: State) {
+        let byte = match state ^ self.invert {
+            State::Off => 0x00,
+            State::On => 0xff,
+        };
+        self.data.fill([byte; 11]);
+        self.clear_padding();
+    }
+
+    /// Turn off all pixels
+    pub fn clear(&mut self) {
+        self.fill(State::Off);
+    }
+
+    /// Invert the state of all pixels
+    pub fn invert(&mut self) {
+        for byte in self.data.as_flattened_mut() {
+            *byte = !*byte;
+        }
+        self.clear_padding();
+    }
+
+    /// Turn off the columns after the width of a [`Bitmap`] in the last byte
+    fn clear_padding(&mut self) {
+        let used = self.width % 8;
+        if let (Some(last), true) = (self.data.last_mut(), used != 0) {
+            for byte in last {
+                *byte &= !(0xff >> used);
+            }
+        }
+    }
+
+    /// Move the content by `dx` pixels to the right and `dy` pixels down
+    ///
+    /// Negative values move the content to the left or up.
+    /// Pixels moved out of the message are discarded, uncovered pixels are turned off.
+    pub fn shift(&mut self, dx: isize, dy: isize) {
+        self.remap(|x, y| {
+            Some((
+                x.checked_add_signed(dx.checked_neg()?)?,
+                y.checked_add_signed(dy.checked_neg()?)?,
+            ))
+        });
+    }
+
+    /// Rotate the content by `dx` pixels to the right and `dy` pixels down
+    ///
+    /// Like [`shift`](Self::shift), but pixels moved out of the message
+    /// reappear on the opposite side.
+    pub fn scroll(&mut self, dx: isize, dy: isize) {
+        fn wrap(offset: isize, len: usize) -> usize {
+            let n = offset.unsigned_abs() % len;
+            if offset < 0 {
+                len - n
+            } else {
+                n
+            }
+        }
+
+        let width = self.width();
+        if width == 0 {
+            return;
+        }
+        let (dx, dy) = (wrap(dx, width), wrap(dy, 11));
+        self.remap(|x, y| Some(((x + width - dx) % width, (y + 11 - dy) % 11)));
+    }
+
+    /// Mirror the content at the vertical axis
+    pub fn flip_horizontal(&mut self) {
+        let width = self.width();
+        self.remap(|x, y| Some((width - 1 - x, y)));
+    }
+
+    /// Mirror the content at the horizontal axis
+    pub fn flip_vertical(&mut self) {
+        self.remap(|x, y| Some((x, 10 - y)));
+    }
+
+    /// Copy the pixels of `source` into this message at point (`x`, `y`)
+    ///
+    /// Pixels outside of this message are ignored.
+    /// Use [`to_bitmap`](Self::to_bitmap) to copy from another message.
+    pub fn blit(&mut self, source: &Bitmap, (x, y): (usize, usize)) {
+        for sx in 0..source.width() {
+            for sy in 0..11 {
+                let target = x.checked_add(sx).zip(y.checked_add(sy));
+                if let (Some(state), Some(target)) = (source.get((sx, sy)), target) {
+                    self.set(target, state);
+                }
+            }
+        }
+    }
+
+    /// Set every pixel to the state of the pixel returned by `source`
+    ///
+    /// Pixels are turned off if `source` returns `None` or an out of bounds point.
+    fn remap(&mut self, source: impl Fn(usize, usize) -> Option<(usize, usize)>) {
+        let original = self.data.to_vec();
+        for x in 0..self.width() {
+            for y in 0..11 {
+                let state = source(x, y)
+                    .and_then(|point| get_pixel(&original, point))
+                    .map_or(State::Off, |state| state ^ self.invert);
+                self.set((x, y), state);
+            }
+        }
+    }
+
     #[cfg(feature = "embedded-graphics")]
     fn set_embedded_graphics(&mut self, point: Point, color: BinaryColor) -> Option<()> {
         let x = point.x.try_into().ok()?;
@@ -482,6 +1121,218 @@ impl MessageBuffer<'_> {
     }
 }
 
+/// A read-only view of a message in a [`PayloadBuffer`]
+///
+/// Returned by [`PayloadBuffer::messages`].
+#[derive(Debug, Clone, Copy)]
+pub struct MessageView<'a> {
+    style: Style,
+    data: &'a [[u8; 11]],
+}
+
+impl MessageView<'_> {
+    /// Return the style of the message
+    pub fn style(&self) -> Style {
+        self.style
+    }
+
+    /// Return the width of the message in pixels
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.data.len() * 8
+    }
+
+    /// Get the state of the pixel at point (`x`, `y`)
+    ///
+    /// Returns `None` if the pixel is out of bounds.
+    #[must_use]
+    pub fn get(&self, (x, y): (usize, usize)) -> Option<State> {
+        get_pixel(self.data, (x, y)).map(|state| state ^ self.style.invert)
+    }
+
+    /// Copy the content of the message into a new [`Bitmap`]
+    pub fn to_bitmap(&self) -> Bitmap {
+        Bitmap::from_message_data(self.data, self.width(), self.style.invert)
+    }
+
+    /// Encode the content of the message as hex string, see [`Bitmap::from_hex`]
+    #[must_use]
+    pub fn to_hex(&self) -> String {
+        encode_hex(self.data, self.style.invert)
+    }
+}
+
+/// An owned image with a height of 11 pixels
+///
+/// The pixels are stored in the same layout as the messages in a payload,
+/// so content can be rendered before it is added to a [`PayloadBuffer`].
+/// With the `embedded-graphics` feature it can be used as a `DrawTarget` and as an `ImageDrawable`.
+/// ```
+/// # #[cfg(feature = "embedded-graphics")]
+/// # fn main() {
+/// use badgemagic::{
+///     embedded_graphics::{
+///         geometry::{Point, Size},
+///         pixelcolor::BinaryColor,
+///         primitives::{PrimitiveStyle, Rectangle, Styled},
+///     },
+///     protocol::{Bitmap, PayloadBuffer, Style},
+/// };
+///
+/// let bitmap = Bitmap::from_drawable(&Styled::new(
+///     Rectangle::new(Point::new(2, 2), Size::new(20, 7)),
+///     PrimitiveStyle::with_fill(BinaryColor::On),
+/// ));
+/// assert_eq!(bitmap.width(), 22);
+///
+/// let mut buffer = PayloadBuffer::new();
+/// buffer.add_message_bitmap(Style::default(), &bitmap);
+/// # }
+/// # #[cfg(not(feature = "embedded-graphics"))]
+/// # fn main() {}
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[must_use]
+pub struct Bitmap {
+    width: usize,
+    data: Vec<[u8; 11]>,
+}
+
+impl Bitmap {
+    /// Create a bitmap with `width` columns, all pixels are turned off
+    pub fn new(width: usize) -> Self {
+        Self {
+            width,
+            data: vec![[0; 11]; width.div_ceil(8)],
+        }
+    }
+
+    /// Render `content` into a new bitmap
+    ///
+    /// The bitmap is wide enough to contain the content including its offset from the origin.
+    /// Pixels left of or above the origin are clipped.
+    #[cfg(feature = "embedded-graphics")]
+    pub fn from_drawable(content: &(impl Drawable<Color = BinaryColor> + Dimensions)) -> Self {
+        let bounds = content.bounding_box();
+        let right = i64::from(bounds.top_left.x) + i64::from(bounds.size.width);
+        let mut bitmap = Self::new(right.try_into().unwrap_or_default());
+        // drawing into a bitmap cannot fail
+        let _ = content.draw(&mut bitmap);
+        bitmap
+    }
+
+    /// Load a bitmap from a hex string, as used by other badge tools
+    ///
+    /// Every group of 8 columns is encoded as 22 hex digits, one byte per row from top
+    /// to bottom, the most significant bit is the left column. Whitespace is ignored.
+    /// ```
+    /// use badgemagic::protocol::{Bitmap, State};
+    ///
+    /// let bitmap = Bitmap::from_hex("00386CC6C6FEC6C6C6C600 00FC6666667C6666FC0000").unwrap();
+    /// assert_eq!(bitmap.width(), 16);
+    /// assert_eq!(bitmap.get((2, 1)), Some(State::On));
+    /// assert_eq!(
+    ///     bitmap.to_hex(),
+    ///     "00386CC6C6FEC6C6C6C60000FC6666667C6666FC0000"
+    /// );
+    /// ```
+    pub fn from_hex(hex: &str) -> anyhow::Result<Self> {
+        let digits = hex
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| {
+                c.to_digit(16)
+                    .ok_or_else(|| anyhow::anyhow!("invalid hex digit: {c:?}"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        anyhow::ensure!(
+            digits.len() % 22 == 0,
+            "expected 22 hex digits per group of 8 columns, found {} hex digits",
+            digits.len()
+        );
+        #[allow(clippy::cast_possible_truncation)] // two hex digits fit into a byte
+        let bytes: Vec<_> = digits
+            .chunks(2)
+            .map(|pair| (pair[0] << 4 | pair[1]) as u8)
+            .collect();
+        let data = bytes.as_chunks::<11>().0.to_vec();
+        Ok(Self {
+            width: data.len() * 8,
+            data,
+        })
+    }
+
+    /// Encode the bitmap as hex string, see [`from_hex`](Self::from_hex)
+    ///
+    /// The bitmap is padded to a multiple of 8 columns.
+    #[must_use]
+    pub fn to_hex(&self) -> String {
+        encode_hex(&self.data, false)
+    }
+
+    fn from_message_data(data: &[[u8; 11]], width: usize, invert: bool) -> Self {
+        let mut bitmap = Self {
+            width,
+            data: data.to_vec(),
+        };
+        if invert {
+            bitmap.as_message_buffer().invert();
+        }
+        bitmap
+    }
+
+    /// Return the width of the bitmap in pixels
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Get the state of the pixel at point (`x`, `y`)
+    ///
+    /// Returns `None` if the pixel is out of bounds.
+    #[must_use]
+    pub fn get(&self, (x, y): (usize, usize)) -> Option<State> {
+        if x >= self.width {
+            return None;
+        }
+        get_pixel(&self.data, (x, y))
+    }
+
+    /// Set the state of the pixel at point (`x`, `y`)
+    ///
+    /// Returns `None` if the pixel was out of bounds.
+    pub fn set(&mut self, (x, y): (usize, usize), state: State) -> Option<()> {
+        self.as_message_buffer().set((x, y), state)
+    }
+
+    /// Access the bitmap as a [`MessageBuffer`] to use its editing operations
+    ///
+    /// The message buffer has the width of the bitmap, the padding columns up to the next
+    /// multiple of 8 columns stay off.
+    pub fn as_message_buffer(&mut self) -> MessageBuffer<'_> {
+        MessageBuffer {
+            data: &mut self.data,
+            invert: false,
+            width: self.width,
+        }
+    }
+}
+
+fn encode_hex(data: &[[u8; 11]], invert: bool) -> String {
+    let mask = if invert { 0xff } else { 0x00 };
+    data.as_flattened()
+        .iter()
+        .fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{:02X}", byte ^ mask);
+            hex
+        })
+}
+
+fn get_pixel(data: &[[u8; 11]], (x, y): (usize, usize)) -> Option<State> {
+    let byte = data.get(x / 8)?.get(y)?;
+    Some((byte & (0x80 >> (x % 8)) != 0).into())
+}
+
 /// State of a pixel
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum State {
@@ -490,6 +1341,15 @@ pub enum State {
     On,
 }
 
+impl BitXor<bool> for State {
+    type Output = Self;
+
+    /// Toggle the state if `rhs` is `true`
+    fn bitxor(self, rhs: bool) -> Self::Output {
+        Self::from((self == Self::On) ^ rhs)
+    }
+}
+
 impl From<bool> for State {
     fn from(value: bool) -> Self {
         if value {
@@ -510,12 +1370,22 @@ impl From<BinaryColor> for State {
     }
 }
 
+#[cfg(feature = "embedded-graphics")]
+impl From<State> for BinaryColor {
+    fn from(value: State) -> Self {
+        match value {
+            State::Off => Self::Off,
+            State::On => Self::On,
+        }
+    }
+}
+
 #[cfg(feature = "embedded-graphics")]
 impl Dimensions for MessageBuffer<'_> {
     fn bounding_box(&self) -> embedded_graphics::primitives::Rectangle {
         Rectangle::new(
             Point::zero(),
-            Size::new((self.0.len() * 8).try_into().unwrap(), 11),
+            Size::new(self.width().try_into().unwrap(), 11),
         )
     }
 }
@@ -543,11 +1413,395 @@ impl DrawTarget for MessageBuffer<'_> {
     }
 }
 
+#[cfg(feature = "embedded-graphics")]
+impl GetPixel for MessageBuffer<'_> {
+    type Color = BinaryColor;
+
+    fn pixel(&self, point: Point) -> Option<Self::Color> {
+        let x = point.x.try_into().ok()?;
+        let y = point.y.try_into().ok()?;
+        self.get((x, y)).map(Into::into)
+    }
+}
+
+#[cfg(feature = "embedded-graphics")]
+impl OriginDimensions for Bitmap {
+    fn size(&self) -> Size {
+        Size::new(self.width.try_into().unwrap_or(u32::MAX), 11)
+    }
+}
+
+#[cfg(feature = "embedded-graphics")]
+impl DrawTarget for Bitmap {
+    type Color = BinaryColor;
+
+    type Error = std::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if let (Ok(x), Ok(y)) = (point.x.try_into(), point.y.try_into()) {
+                self.set((x, y), color.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded-graphics")]
+impl GetPixel for Bitmap {
+    type Color = BinaryColor;
+
+    fn pixel(&self, point: Point) -> Option<Self::Color> {
+        let x = point.x.try_into().ok()?;
+        let y = point.y.try_into().ok()?;
+        self.get((x, y)).map(Into::into)
+    }
+}
+
+#[cfg(feature = "embedded-graphics")]
+impl ImageDrawable for Bitmap {
+    type Color = BinaryColor;
+
+    fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        target.fill_contiguous(
+            &self.bounding_box(),
+            self.bounding_box()
+                .points()
+                .map(|point| self.pixel(point).unwrap_or(BinaryColor::Off)),
+        )
+    }
+
+    fn draw_sub_image<D>(&self, target: &mut D, area: &Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        self.draw(&mut target.translated(-area.top_left).clipped(area))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::ops::Range;
 
-    use super::{Brightness, Speed};
+    use zerocopy::U16;
+
+    use super::{Bitmap, Brightness, MessageBuffer, Mode, PayloadBuffer, Speed, State, Style};
+
+    fn on_pixels(message: &MessageBuffer<'_>) -> Vec<(usize, usize)> {
+        (0..message.width())
+            .flat_map(|x| (0..11).map(move |y| (x, y)))
+            .filter(|&point| message.get(point) == Some(State::On))
+            .collect()
+    }
+
+    #[test]
+    fn message_buffer_get_and_set() {
+        let mut payload = PayloadBuffer::new();
+        let mut message = payload.add_message(Style::default(), 2);
+        assert_eq!(message.width(), 16);
+        assert_eq!(message.set((9, 3), State::On), Some(()));
+        assert_eq!(message.set((16, 3), State::On), None);
+        assert_eq!(message.get((9, 3)), Some(State::On));
+        assert_eq!(message.get((9, 4)), Some(State::Off));
+        assert_eq!(message.get((0, 11)), None);
+
+        message.invert();
+        assert_eq!(on_pixels(&message).len(), 16 * 11 - 1);
+        message.clear();
+        assert_eq!(on_pixels(&message), []);
+    }
+
+    #[test]
+    fn message_buffer_shift_and_scroll() {
+        let mut payload = PayloadBuffer::new();
+        let mut message = payload.add_message(Style::default(), 2);
+        message.set((1, 1), State::On);
+        message.set((14, 9), State::On);
+
+        message.shift(2, 1);
+        assert_eq!(on_pixels(&message), [(3, 2)]);
+        message.shift(-3, -2);
+        assert_eq!(on_pixels(&message), [(0, 0)]);
+
+        message.scroll(-1, -1);
+        assert_eq!(on_pixels(&message), [(15, 10)]);
+        message.scroll(17, 12);
+        assert_eq!(on_pixels(&message), [(0, 0)]);
+    }
+
+    #[test]
+    fn message_buffer_flip_and_blit() {
+        let mut payload = PayloadBuffer::new();
+        let mut source = payload.add_message(Style::default(), 1);
+        source.set((1, 2), State::On);
+        source.flip_horizontal();
+        assert_eq!(on_pixels(&source), [(6, 2)]);
+        source.flip_vertical();
+        assert_eq!(on_pixels(&source), [(6, 8)]);
+
+        let mut payload = PayloadBuffer::new();
+        let mut message = payload.add_message(Style::default(), 2);
+        message.fill(State::On);
+        message.blit(&source.to_bitmap(), (4, 2));
+        assert_eq!(message.get((10, 10)), Some(State::On));
+        assert_eq!(message.get((11, 10)), Some(State::Off));
+        assert_eq!(message.get((11, 9)), Some(State::Off));
+        assert_eq!(message.get((12, 8)), Some(State::On));
+        assert_eq!(message.get((3, 8)), Some(State::On));
+
+        // only the columns of the bitmap are copied, far away positions are ignored
+        let bitmap = Bitmap::new(3);
+        message.blit(&bitmap, (0, 0));
+        assert_eq!(message.get((2, 0)), Some(State::Off));
+        assert_eq!(message.get((3, 0)), Some(State::On));
+        message.blit(&bitmap, (usize::MAX, usize::MAX));
+    }
+
+    #[test]
+    fn bitmap_padding_columns_stay_off() {
+        let mut bitmap = Bitmap::new(10);
+        assert_eq!(bitmap.set((9, 0), State::On), Some(()));
+        assert_eq!(bitmap.set((10, 0), State::On), None);
+        assert_eq!(bitmap.get((10, 0)), None);
+
+        let mut payload = PayloadBuffer::new();
+        let message = payload.add_message_bitmap(Style::default(), &bitmap);
+        assert_eq!(message.width(), 16);
+        assert_eq!(message.to_bitmap().width(), 16);
+        assert_eq!(
+            &payload.as_bytes()[64..],
+            [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x40, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+
+        // editing operations don't touch the padding columns
+        let mut edited = Bitmap::new(10);
+        let mut buffer = edited.as_message_buffer();
+        assert_eq!(buffer.width(), 10);
+        buffer.fill(State::On);
+        buffer.invert();
+        buffer.set((0, 0), State::On);
+        buffer.flip_horizontal();
+        buffer.shift(0, 1);
+        buffer.shift(0, -1);
+        assert_eq!(edited, bitmap);
+        assert_eq!(
+            edited.to_hex(),
+            format!("{}40{}", "00".repeat(11), "00".repeat(10))
+        );
+
+        let mut filled = Bitmap::new(10);
+        filled.as_message_buffer().invert();
+        assert_eq!(&filled.to_hex()[22..24], "C0");
+    }
+
+    #[test]
+    fn inverted_message() {
+        let mut payload = PayloadBuffer::new();
+        let mut message = payload.add_message(Style::default().invert(), 2);
+        assert_eq!(on_pixels(&message), []);
+        message.set((0, 0), State::On);
+        message.shift(1, 0);
+        assert_eq!(on_pixels(&message), [(1, 0)]);
+        assert_eq!(message.to_bitmap().get((1, 0)), Some(State::On));
+
+        let mut bitmap = Bitmap::new(10);
+        bitmap.set((9, 1), State::On);
+        payload.add_message_bitmap(Style::default().invert(), &bitmap);
+
+        let data = &payload.as_bytes()[64..];
+        assert_eq!(data[..2], [0xbf, 0xff]);
+        assert!(data[2..11].iter().all(|&byte| byte == 0xff));
+        // the padding columns in the last byte are inverted as well
+        assert_eq!(data[33..35], [0xff, 0xbf]);
+    }
+
+    #[test]
+    fn hex_messages() {
+        let hex = "00386CC6C6FEC6C6C6C600\n00fc6666667c6666fc0000";
+        let mut payload = PayloadBuffer::new();
+        let message = payload.add_message_hex(Style::default(), hex).unwrap();
+        assert_eq!(message.width(), 16);
+        assert_eq!(
+            message.to_hex(),
+            "00386CC6C6FEC6C6C6C60000FC6666667C6666FC0000"
+        );
+
+        // the hex string contains the content before the inversion
+        let mut message = payload
+            .add_message_hex(Style::default().invert(), "80000000000000000000FF")
+            .unwrap();
+        assert_eq!(message.get((0, 0)), Some(State::On));
+        message.set((1, 0), State::On);
+        assert_eq!(message.to_hex(), "C0000000000000000000FF");
+        assert_eq!(payload.as_bytes()[64 + 22..][..2], [0x3f, 0xff]);
+        let view = payload.messages().nth(1).unwrap();
+        assert_eq!(view.to_hex(), "C0000000000000000000FF");
+        assert_eq!(view.to_bitmap().to_hex(), view.to_hex());
+
+        assert!(Bitmap::from_hex("00").is_err());
+        assert!(Bitmap::from_hex("00386CC6C6FEC6C6C6C6+0").is_err());
+        assert_eq!(Bitmap::from_hex("").unwrap().width(), 0);
+    }
+
+    #[test]
+    fn read_back_messages() {
+        let style = Style::default().border().with_speed(Speed::Fps7_5).invert();
+        let mut payload = PayloadBuffer::new();
+        payload.set_brightness(Brightness::Half);
+        payload.add_message(Style::default(), 1);
+        payload.add_message(style, 3).set((17, 10), State::On);
+
+        assert_eq!(payload.brightness(), Brightness::Half);
+        assert_eq!(payload.num_messages(), 2);
+        let messages: Vec<_> = payload.messages().collect();
+        assert_eq!(messages[0].style(), Style::default());
+        assert_eq!(messages[0].width(), 8);
+
+        let message = messages[1];
+        assert_eq!(message.style(), style);
+        assert!(message.style().is_border() && message.style().is_invert());
+        assert!(!message.style().is_blink());
+        assert_eq!(message.style().speed(), Speed::Fps7_5);
+        assert_eq!(message.style().mode(), Mode::Left);
+        assert_eq!(message.width(), 24);
+        assert_eq!(message.get((17, 10)), Some(State::On));
+        assert_eq!(message.get((16, 10)), Some(State::Off));
+        assert_eq!(message.to_bitmap().get((17, 10)), Some(State::On));
+    }
+
+    #[test]
+    fn load_payload_bytes() {
+        let style = Style::default().blink().with_mode(Mode::Drop);
+        let mut payload = PayloadBuffer::new();
+        payload.set_brightness(Brightness::OneQuarter);
+        payload.add_message(style, 2).set((12, 5), State::On);
+        payload.add_message(Style::default(), 1);
+
+        let bytes = payload.as_bytes().to_vec();
+        let padded = payload.into_padded_bytes();
+        for data in [&bytes[..], padded.as_ref()] {
+            let loaded = PayloadBuffer::from_bytes(data).unwrap();
+            assert_eq!(loaded.as_bytes(), bytes);
+            assert_eq!(loaded.brightness(), Brightness::OneQuarter);
+            assert_eq!(loaded.num_messages(), 2);
+            let message = loaded.messages().next().unwrap();
+            assert_eq!(message.style(), style);
+            assert_eq!(message.get((12, 5)), Some(State::On));
+        }
+
+        assert!(PayloadBuffer::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(PayloadBuffer::from_bytes(&[0; 64]).is_err());
+
+        // trailing messages without columns are kept, unless they have the all zero style
+        let mut payload = PayloadBuffer::new();
+        payload.add_message(style, 1);
+        payload.add_message(Style::default(), 0);
+        payload.add_message(Style::default().with_speed(Speed::Fps1_2), 0);
+        let loaded = PayloadBuffer::from_bytes(payload.as_bytes()).unwrap();
+        assert_eq!(loaded.num_messages(), 2);
+        assert_eq!(loaded.messages().nth(1).unwrap().width(), 0);
+        let mut trailing = bytes.clone();
+        trailing.push(1);
+        assert!(PayloadBuffer::from_bytes(&trailing).is_err());
+    }
+
+    #[test]
+    fn edit_payload_messages() {
+        let styles = [
+            Style::default().blink(),
+            Style::default().border().with_mode(Mode::Center),
+            Style::default().with_speed(Speed::Fps15).invert(),
+        ];
+        let mut payload = PayloadBuffer::new();
+        for (index, &style) in styles.iter().enumerate() {
+            payload
+                .add_message(style, index + 1)
+                .set((index, 0), State::On);
+        }
+        let header = |payload: &PayloadBuffer| {
+            let header = payload.header();
+            (
+                header.blink,
+                header.border,
+                header.speed_and_mode,
+                header.message_length.map(U16::get),
+            )
+        };
+        let message = |payload: &mut PayloadBuffer, index| {
+            (
+                payload.style(index),
+                payload.message_buffer(index).to_bitmap(),
+            )
+        };
+        let mut expected = PayloadBuffer::new();
+        let original: Vec<_> = (0..3).map(|index| message(&mut payload, index)).collect();
+
+        payload.move_message(0, 2);
+        for index in [1, 2, 0] {
+            expected.add_message_bitmap(original[index].0, &original[index].1);
+        }
+        assert_eq!(header(&payload), header(&expected));
+        assert_eq!(payload.as_bytes(), expected.as_bytes());
+
+        payload.remove_message(1);
+        assert_eq!(payload.num_messages(), 2);
+        assert_eq!(message(&mut payload, 0), original[1]);
+        assert_eq!(message(&mut payload, 1), original[0]);
+        assert_eq!(payload.as_bytes().len(), 64 + 3 * 11);
+
+        payload.set_message_style(1, Style::default().invert());
+        assert_eq!(message(&mut payload, 1).1, original[0].1);
+        assert_eq!(payload.as_bytes()[64 + 22], 0x7f);
+
+        let mut bitmap = Bitmap::new(20);
+        bitmap.set((19, 10), State::On);
+        payload.replace_message_bitmap(0, &bitmap);
+        assert_eq!(payload.style(0), styles[1]);
+        assert_eq!(payload.header().message_length[0].get(), 3);
+        assert_eq!(payload.message_buffer(0).get((19, 10)), Some(State::On));
+        assert_eq!(message(&mut payload, 1).1, original[0].1);
+    }
+
+    #[cfg(feature = "embedded-graphics")]
+    #[test]
+    fn bitmap_matches_drawable_message() {
+        use embedded_graphics::{
+            geometry::{Point, Size},
+            image::Image,
+            pixelcolor::BinaryColor,
+            primitives::{PrimitiveStyle, Rectangle, Styled},
+            Drawable,
+        };
+
+        let content = Styled::new(
+            Rectangle::new(Point::new(3, 2), Size::new(9, 7)),
+            PrimitiveStyle::with_stroke(BinaryColor::On, 1),
+        );
+        let bitmap = Bitmap::from_drawable(&content);
+        assert_eq!(bitmap.width(), 12);
+
+        let mut expected = PayloadBuffer::new();
+        expected.add_message_drawable(Style::default(), &content);
+        let mut payload = PayloadBuffer::new();
+        payload.add_message_bitmap(Style::default(), &bitmap);
+        assert_eq!(payload.as_bytes()[64..], expected.as_bytes()[64..]);
+
+        let mut payload = PayloadBuffer::new();
+        let mut message = payload.add_message(Style::default(), 2);
+        message.fill(State::On);
+        Image::new(&bitmap, Point::zero())
+            .draw(&mut message)
+            .unwrap();
+        assert_eq!(message.get((0, 0)), Some(State::Off));
+        assert_eq!(message.get((3, 2)), Some(State::On));
+        assert_eq!(message.get((12, 0)), Some(State::On));
+    }
 
     #[test]
     fn speed_to_u8_and_back() {
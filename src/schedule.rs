@@ -0,0 +1,567 @@
+//! Playlists that select a configuration depending on the time
+//!
+//! A [`Playlist`] consists of entries, each referencing a configuration file
+//! and describing when it should be shown on the badge.
+//! The first entry that is active at a given time is selected.
+//! ```
+//! use badgemagic::schedule::Playlist;
+//! use time::{Date, Month};
+//!
+//! let playlist: Playlist = toml::from_str(
+//!     r#"
+//!     [[entry]]
+//!     config = "keynote.toml"
+//!     date = "2024-05-02"
+//!     from = "09:00"
+//!     until = "10:30"
+//!
+//!     [[entry]]
+//!     config = "lunch.toml"
+//!     cron = "* 12 * * mon-fri"
+//!
+//!     [[entry]]
+//!     config = "default.toml"
+//!     "#,
+//! )
+//! .unwrap();
+//!
+//! let date = Date::from_calendar_date(2024, Month::May, 2).unwrap();
+//! let at = |hour, minute| date.with_hms(hour, minute, 0).unwrap().assume_utc();
+//! assert_eq!(playlist.active(at(9, 15)), Some(0));
+//! assert_eq!(playlist.active(at(12, 0)), Some(1));
+//! assert_eq!(playlist.active(at(10, 30)), Some(2));
+//! assert_eq!(playlist.next_change(at(9, 15)), Some(at(10, 30)));
+//! ```
+
+use std::{
+    fmt::{self, Display},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use time::{Date, Duration, Month, OffsetDateTime, Weekday};
+
+#[cfg(feature = "schema")]
+use std::borrow::Cow;
+
+/// How far [`Playlist::next_change`] looks into the future
+const MAX_LOOKAHEAD: Duration = Duration::days(366);
+
+/// List of configurations with the time they should be shown
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct Playlist {
+    #[serde(rename = "entry")]
+    pub entries: Vec<Entry>,
+}
+
+/// A configuration and the time it is active
+///
+/// All conditions that are set have to match. An entry without conditions is always active.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct Entry {
+    /// Path to the configuration file, relative to the directory of the playlist
+    pub config: PathBuf,
+
+    /// Cron-like rule, the entry is active during every matching minute
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cron: Option<Cron>,
+
+    /// Start of the daily time window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<TimeOfDay>,
+
+    /// End of the daily time window (exclusive), the window wraps around midnight if it
+    /// ends before it starts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<TimeOfDay>,
+
+    /// Days of the week the entry is active on, every day if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<Day>,
+
+    /// The only date the entry is active on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<CalendarDate>,
+}
+
+impl Playlist {
+    /// Return the index of the first entry that is active at `at`
+    ///
+    /// The local time of `at` is used, convert it to the desired offset first.
+    #[must_use]
+    pub fn active(&self, at: OffsetDateTime) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.is_active(at))
+    }
+
+    /// Return the start of the next minute in which a different entry is active
+    ///
+    /// Returns `None` if the active entry does not change within the next year.
+    #[must_use]
+    pub fn next_change(&self, at: OffsetDateTime) -> Option<OffsetDateTime> {
+        let active = self.active(at);
+        let start = at.replace_second(0).ok()?.replace_nanosecond(0).ok()?;
+        (1..=MAX_LOOKAHEAD.whole_minutes())
+            .map(|minutes| start + Duration::minutes(minutes))
+            .find(|&at| self.active(at) != active)
+    }
+}
+
+impl Entry {
+    /// Create an entry that is always active
+    #[must_use]
+    pub fn new(config: PathBuf) -> Self {
+        Self {
+            config,
+            cron: None,
+            from: None,
+            until: None,
+            days: Vec::new(),
+            date: None,
+        }
+    }
+
+    /// Return whether the entry is active at `at`
+    #[must_use]
+    pub fn is_active(&self, at: OffsetDateTime) -> bool {
+        let time = TimeOfDay {
+            hour: at.hour(),
+            minute: at.minute(),
+        };
+        let in_window = match (self.from, self.until) {
+            (Some(from), Some(until)) if until < from => time >= from || time < until,
+            (from, until) => {
+                from.is_none_or(|from| time >= from) && until.is_none_or(|until| time < until)
+            }
+        };
+        in_window
+            && (self.days.is_empty()
+                || self
+                    .days
+                    .iter()
+                    .any(|&day| Weekday::from(day) == at.weekday()))
+            && self.date.is_none_or(|date| date.0 == at.date())
+            && self.cron.as_ref().is_none_or(|cron| cron.matches(at))
+    }
+}
+
+/// Day of the week
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Day {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+impl From<Day> for Weekday {
+    fn from(value: Day) -> Self {
+        match value {
+            Day::Mon => Self::Monday,
+            Day::Tue => Self::Tuesday,
+            Day::Wed => Self::Wednesday,
+            Day::Thu => Self::Thursday,
+            Day::Fri => Self::Friday,
+            Day::Sat => Self::Saturday,
+            Day::Sun => Self::Sunday,
+        }
+    }
+}
+
+/// Time of the day with minute precision, written as `HH:MM`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeOfDay {
+    hour: u8,
+    minute: u8,
+}
+
+impl TimeOfDay {
+    /// Create a time of the day, returns `None` for an invalid time
+    #[must_use]
+    pub fn new(hour: u8, minute: u8) -> Option<Self> {
+        (hour < 24 && minute < 60).then_some(Self { hour, minute })
+    }
+}
+
+impl FromStr for TimeOfDay {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (hour, minute) = s.split_once(':').context("expected a time like 09:30")?;
+        anyhow::ensure!(minute.len() == 2, "expected a time like 09:30, got {s:?}");
+        Self::new(hour.parse()?, minute.parse()?).with_context(|| format!("invalid time {s:?}"))
+    }
+}
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<TimeOfDay> for String {
+    fn from(value: TimeOfDay) -> Self {
+        value.to_string()
+    }
+}
+
+impl Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.minute)
+    }
+}
+
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for TimeOfDay {
+    fn schema_name() -> Cow<'static, str> {
+        "TimeOfDay".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "description": "Time of the day (`HH:MM`)",
+            "type": "string",
+            "pattern": "^[0-9]{1,2}:[0-9]{2}$",
+        })
+    }
+}
+
+/// A date, written as `YYYY-MM-DD`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CalendarDate(pub Date);
+
+impl FromStr for CalendarDate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(3, '-');
+        let mut part = || parts.next().context("expected a date like 2024-05-02");
+        let (year, month, day) = (part()?.parse()?, part()?.parse::<u8>()?, part()?.parse()?);
+        Ok(Self(
+            Date::from_calendar_date(year, Month::try_from(month)?, day)
+                .with_context(|| format!("invalid date {s:?}"))?,
+        ))
+    }
+}
+
+impl TryFrom<String> for CalendarDate {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<CalendarDate> for String {
+    fn from(value: CalendarDate) -> Self {
+        value.to_string()
+    }
+}
+
+impl Display for CalendarDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let date = self.0;
+        write!(
+            f,
+            "{:04}-{:02}-{:02}",
+            date.year(),
+            u8::from(date.month()),
+            date.day()
+        )
+    }
+}
+
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for CalendarDate {
+    fn schema_name() -> Cow<'static, str> {
+        "CalendarDate".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "description": "Date (`YYYY-MM-DD`)",
+            "type": "string",
+            "pattern": "^[0-9]{4}-[0-9]{1,2}-[0-9]{1,2}$",
+        })
+    }
+}
+
+/// Cron-like rule with five fields: minute, hour, day of the month, month and day of the week
+///
+/// Every field is a `*`, a number, a range (`1-5`) or a list of them (`1,3,5`),
+/// optionally followed by a step (`*/15`). Months and days of the week can be written
+/// by their names (`jan`, `mon`). Like in cron, a day matches if the day of the month
+/// *or* the day of the week matches, if both are restricted.
+/// ```
+/// use badgemagic::schedule::Cron;
+///
+/// let lunch: Cron = "*/15 12-13 * * mon-fri".parse().unwrap();
+/// assert_eq!(lunch.to_string(), "*/15 12-13 * * mon-fri");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Cron {
+    source: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Cron {
+    /// Return whether the minute of `at` matches the rule
+    #[must_use]
+    pub fn matches(&self, at: OffsetDateTime) -> bool {
+        let bit = |mask: u64, value: u8| mask & (1 << value) != 0;
+        let day = bit(self.days, at.day());
+        let weekday = bit(self.weekdays, at.weekday().number_days_from_sunday());
+        let day = match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        };
+        bit(self.minutes, at.minute())
+            && bit(self.hours, at.hour())
+            && bit(self.months, at.month().into())
+            && day
+    }
+}
+
+/// Parse a cron field into a bitmask of the matching values
+fn parse_cron_field(field: &str, min: u8, max: u8, names: &[&str]) -> Result<u64> {
+    let value = |value: &str| -> Result<u8> {
+        let value = match names
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(value))
+        {
+            #[allow(clippy::cast_possible_truncation)] // at most 12 names
+            Some(index) => index as u8 + min,
+            None => value
+                .parse()
+                .with_context(|| format!("invalid value {value:?}"))?,
+        };
+        anyhow::ensure!(
+            (min..=max).contains(&value),
+            "value {value} out of range {min}-{max}"
+        );
+        Ok(value)
+    };
+
+    let mut mask = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse().context("invalid step")?),
+            None => (part, 1),
+        };
+        anyhow::ensure!(step > 0, "step must be greater than 0");
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (value(start)?, value(end)?)
+        } else {
+            let start = value(range)?;
+            (start, if step > 1 { max } else { start })
+        };
+        anyhow::ensure!(start <= end, "invalid range {range:?}");
+        for value in (start..=end).step_by(step) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+impl FromStr for Cron {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const MONTHS: &[&str] = &[
+            "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+        ];
+        const WEEKDAYS: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+        let fields: Vec<_> = s.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            anyhow::bail!(
+                "expected 5 fields in cron rule, found {} fields",
+                fields.len()
+            );
+        };
+        let field = |name: &str, field: &str, min, max, names| {
+            parse_cron_field(field, min, max, names).with_context(|| format!("{name} field"))
+        };
+
+        let mut weekday_mask = field("day of week", weekdays, 0, 7, WEEKDAYS)?;
+        // 0 and 7 are both sunday
+        if weekday_mask & (1 << 7) != 0 {
+            weekday_mask = weekday_mask & !(1 << 7) | 1;
+        }
+        Ok(Self {
+            source: s.to_owned(),
+            minutes: field("minute", minutes, 0, 59, &[])?,
+            hours: field("hour", hours, 0, 23, &[])?,
+            days: field("day of month", days, 1, 31, &[])?,
+            months: field("month", months, 1, 12, MONTHS)?,
+            weekdays: weekday_mask,
+            any_day: days.starts_with('*'),
+            any_weekday: weekdays.starts_with('*'),
+        })
+    }
+}
+
+impl TryFrom<String> for Cron {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Cron> for String {
+    fn from(value: Cron) -> Self {
+        value.source
+    }
+}
+
+impl Display for Cron {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for Cron {
+    fn schema_name() -> Cow<'static, str> {
+        "Cron".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "description": "Cron-like rule: minute, hour, day of month, month and day of week",
+            "type": "string",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use time::{Date, Month, OffsetDateTime};
+
+    use super::{CalendarDate, Cron, Day, Entry, Playlist, TimeOfDay};
+
+    fn at(day: u8, hour: u8, minute: u8) -> OffsetDateTime {
+        // 2024-07-01 is a monday
+        Date::from_calendar_date(2024, Month::July, day)
+            .unwrap()
+            .with_hms(hour, minute, 0)
+            .unwrap()
+            .assume_utc()
+    }
+
+    #[test]
+    fn parse_cron() {
+        let cron: Cron = "0,30 9-17/2 * * 1-5".parse().unwrap();
+        assert!(cron.matches(at(1, 9, 30)));
+        assert!(cron.matches(at(5, 17, 0)));
+        assert!(!cron.matches(at(1, 10, 0)));
+        assert!(!cron.matches(at(1, 9, 15)));
+        assert!(!cron.matches(at(6, 9, 0)));
+
+        // day of month or day of week
+        let cron: Cron = "* * 6 jul sun".parse().unwrap();
+        assert!(cron.matches(at(6, 0, 0)));
+        assert!(cron.matches(at(7, 0, 0)));
+        assert!(!cron.matches(at(8, 0, 0)));
+        let numeric: Cron = "* * 6 7 7".parse().unwrap();
+        for day in 1..=31 {
+            assert_eq!(numeric.matches(at(day, 0, 0)), cron.matches(at(day, 0, 0)));
+        }
+
+        assert!("* * * *".parse::<Cron>().is_err());
+        assert!("60 * * * *".parse::<Cron>().is_err());
+        assert!("* * * * fri-mon".parse::<Cron>().is_err());
+        assert!("*/0 * * * *".parse::<Cron>().is_err());
+    }
+
+    #[test]
+    fn entry_conditions() {
+        let entry = Entry {
+            from: "22:00".parse().ok(),
+            until: "02:00".parse().ok(),
+            days: vec![Day::Mon, Day::Tue],
+            ..Entry::new("night.toml".into())
+        };
+        assert!(entry.is_active(at(1, 23, 59)));
+        assert!(entry.is_active(at(2, 1, 59)));
+        assert!(!entry.is_active(at(2, 2, 0)));
+        assert!(!entry.is_active(at(1, 21, 59)));
+        assert!(!entry.is_active(at(3, 23, 0)));
+
+        let entry = Entry {
+            from: TimeOfDay::new(9, 0),
+            date: "2024-07-03".parse().ok(),
+            ..Entry::new("talk.toml".into())
+        };
+        assert!(entry.is_active(at(3, 9, 0)));
+        assert!(!entry.is_active(at(3, 8, 59)));
+        assert!(!entry.is_active(at(4, 9, 0)));
+
+        assert!("24:00".parse::<TimeOfDay>().is_err());
+        assert!("9:5".parse::<TimeOfDay>().is_err());
+        assert!("2024-02-30".parse::<CalendarDate>().is_err());
+    }
+
+    #[test]
+    fn round_trip_playlist() {
+        let playlist = Playlist {
+            entries: vec![
+                Entry {
+                    cron: "*/5 * * * *".parse().ok(),
+                    from: TimeOfDay::new(8, 30),
+                    days: vec![Day::Sat],
+                    date: "2024-07-06".parse().ok(),
+                    ..Entry::new("a.toml".into())
+                },
+                Entry::new("b.json".into()),
+            ],
+        };
+        let toml = toml::to_string(&playlist).unwrap();
+        assert!(toml.contains("from = \"08:30\""));
+        assert_eq!(toml::from_str::<Playlist>(&toml).unwrap(), playlist);
+    }
+
+    #[test]
+    fn next_change() {
+        let playlist = Playlist {
+            entries: vec![Entry {
+                cron: "0 12 * * *".parse().ok(),
+                ..Entry::new("noon.toml".into())
+            }],
+        };
+        assert_eq!(playlist.active(at(1, 11, 0)), None);
+        assert_eq!(playlist.next_change(at(1, 11, 0)), Some(at(1, 12, 0)));
+        assert_eq!(playlist.next_change(at(1, 12, 0)), Some(at(1, 12, 1)));
+
+        let always = Playlist {
+            entries: vec![Entry::new("always.toml".into())],
+        };
+        assert_eq!(always.next_change(at(1, 0, 0)), None);
+    }
+}
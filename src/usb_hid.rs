@@ -5,7 +5,7 @@ use std::sync::Arc;
 use anyhow::{Context, Result};
 use hidapi::{DeviceInfo, HidApi, HidDevice};
 
-use crate::protocol::PayloadBuffer;
+use crate::protocol::{PayloadBuffer, MAX_PAYLOAD_SIZE};
 
 enum DeviceType {
     // rename if we add another device type
@@ -96,10 +96,13 @@ fn write_raw(device: &HidDevice, data: &[u8]) -> Result<()> {
     );
 
     // the device will brick itself if the payload is too long (more then 8192 bytes)
-    anyhow::ensure!(data.len() <= 8192, "payload too long (max 8192 bytes)");
+    anyhow::ensure!(
+        data.len() <= MAX_PAYLOAD_SIZE,
+        "payload too long (max {MAX_PAYLOAD_SIZE} bytes)"
+    );
 
     // just to be sure
-    assert!(data.len() <= 8192);
+    assert!(data.len() <= MAX_PAYLOAD_SIZE);
 
     let written: usize;
 
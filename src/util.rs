@@ -4,6 +4,13 @@ use embedded_graphics::Drawable;
 
 use self::layout::ZStack;
 
+pub mod animation;
+pub mod preview;
+pub mod raster;
+#[cfg(feature = "svg")]
+pub mod svg;
+pub mod text;
+
 /// Drawable layout extension
 pub trait DrawableLayoutExt: Drawable + Sized {
     /// Draw a
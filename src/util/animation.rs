@@ -0,0 +1,256 @@
+//! Frame based animations for [`Mode::Fast`](crate::protocol::Mode::Fast)
+
+use std::time::Duration;
+
+use embedded_graphics::{
+    draw_target::{DrawTarget, DrawTargetExt},
+    geometry::{Dimensions, Point, Size},
+    pixelcolor::BinaryColor,
+    primitives::Rectangle,
+    Drawable,
+};
+
+use crate::protocol::{PayloadBuffer, Speed, MAX_PAYLOAD_SIZE};
+
+/// Width of a single frame
+pub const FRAME_WIDTH: u32 = 44;
+
+/// Height of a single frame
+pub const FRAME_HEIGHT: u32 = 11;
+
+/// Distance between the start of two frames
+pub const FRAME_STRIDE: u32 = 48;
+
+/// Number of payload bytes used by a single frame
+pub const FRAME_SIZE: usize = (FRAME_STRIDE / 8 * FRAME_HEIGHT) as usize;
+
+/// Return the number of frames that fit into the remaining space of the `payload`
+#[must_use]
+pub fn max_frames(payload: &PayloadBuffer) -> usize {
+    MAX_PAYLOAD_SIZE.saturating_sub(payload.as_bytes().len()) / FRAME_SIZE
+}
+
+/// A sequence of 44x11 pixel frames
+///
+/// In [`Mode::Fast`](crate::protocol::Mode::Fast) the badge shows one screen every 48 pixels,
+/// leaving a 4 pixel gap between the screens. The frames are placed accordingly
+/// and clipped to the size of the display.
+/// ```
+/// # use badgemagic::protocol::{Mode, PayloadBuffer, Style};
+/// use badgemagic::{
+///     embedded_graphics::{
+///         geometry::{Point, Size},
+///         pixelcolor::BinaryColor,
+///         primitives::{PrimitiveStyle, Rectangle, Styled},
+///     },
+///     util::animation::{max_frames, FrameSequence},
+/// };
+///
+/// let mut payload = PayloadBuffer::new();
+/// let mut frames: FrameSequence<_> = (0..44)
+///     .map(|x| {
+///         Styled::new(
+///             Rectangle::new(Point::new(x, 0), Size::new(1, 11)),
+///             PrimitiveStyle::with_fill(BinaryColor::On),
+///         )
+///     })
+///     .collect();
+/// frames.reduce(max_frames(&payload));
+/// payload.add_message_drawable(Style::default().with_mode(Mode::Fast), &frames);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[must_use]
+pub struct FrameSequence<D> {
+    frames: Vec<D>,
+}
+
+impl<D> Default for FrameSequence<D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D> FrameSequence<D> {
+    /// Create an empty sequence
+    pub fn new() -> Self {
+        Self { frames: Vec::new() }
+    }
+
+    /// Append a frame to the sequence
+    pub fn push(&mut self, frame: D) {
+        self.frames.push(frame);
+    }
+
+    /// Return the number of frames
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Return whether the sequence has no frames
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Return the frames of the sequence
+    #[must_use]
+    pub fn frames(&self) -> &[D] {
+        &self.frames
+    }
+
+    /// Skip frames evenly until at most `max_frames` are left
+    pub fn reduce(&mut self, max_frames: usize) {
+        let len = self.frames.len();
+        if len <= max_frames {
+            return;
+        }
+        let mut keep = vec![false; len];
+        for i in 0..max_frames {
+            keep[i * len / max_frames] = true;
+        }
+        let mut keep = keep.into_iter();
+        self.frames.retain(|_| keep.next().unwrap_or_default());
+    }
+}
+
+impl<D> FromIterator<D> for FrameSequence<D> {
+    fn from_iter<T: IntoIterator<Item = D>>(iter: T) -> Self {
+        Self {
+            frames: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<D> Dimensions for FrameSequence<D> {
+    fn bounding_box(&self) -> Rectangle {
+        let count = u32::try_from(self.frames.len()).unwrap_or(u32::MAX);
+        Rectangle::new(
+            Point::zero(),
+            Size::new(
+                count.saturating_mul(FRAME_STRIDE),
+                if count == 0 { 0 } else { FRAME_HEIGHT },
+            ),
+        )
+    }
+}
+
+impl<D> Drawable for FrameSequence<D>
+where
+    D: Drawable<Color = BinaryColor>,
+{
+    type Color = BinaryColor;
+
+    type Output = ();
+
+    fn draw<T>(&self, target: &mut T) -> Result<Self::Output, T::Error>
+    where
+        T: DrawTarget<Color = Self::Color>,
+    {
+        let frame = Rectangle::new(Point::zero(), Size::new(FRAME_WIDTH, FRAME_HEIGHT));
+        for (x, drawable) in (0..).step_by(FRAME_STRIDE as usize).zip(&self.frames) {
+            drawable.draw(&mut target.translated(Point::new(x, 0)).clipped(&frame))?;
+        }
+        Ok(())
+    }
+}
+
+/// Map frames with the given `delays` onto the fixed frame rate of `speed`
+///
+/// Returns the index of the source frame for every frame shown by the badge.
+/// ```
+/// use std::time::Duration;
+///
+/// use badgemagic::{protocol::Speed, util::animation::resample};
+///
+/// let delays = [Duration::from_millis(400), Duration::from_millis(133)];
+/// assert_eq!(resample(&delays, Speed::Fps7_5), [0, 0, 0, 1]);
+/// ```
+#[must_use]
+pub fn resample(delays: &[Duration], speed: Speed) -> Vec<usize> {
+    let Some(&first) = delays.first() else {
+        return Vec::new();
+    };
+    let interval = 1.0 / f64::from(speed.frames_per_second());
+    let total: f64 = delays.iter().map(Duration::as_secs_f64).sum();
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let count = ((total / interval).round() as usize).max(1);
+
+    let mut frames = Vec::with_capacity(count);
+    let (mut frame, mut frame_end) = (0, first.as_secs_f64());
+    for i in 0..count {
+        #[allow(clippy::cast_precision_loss)]
+        let time = (i as f64 + 0.5) * interval;
+        while time >= frame_end && frame + 1 < delays.len() {
+            frame += 1;
+            frame_end += delays[frame].as_secs_f64();
+        }
+        frames.push(frame);
+    }
+    frames
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use embedded_graphics::{
+        geometry::{Point, Size},
+        pixelcolor::BinaryColor,
+        primitives::{PrimitiveStyle, Rectangle, Styled},
+    };
+
+    use super::{max_frames, resample, FrameSequence, FRAME_SIZE};
+    use crate::protocol::{PayloadBuffer, Speed, Style, MAX_PAYLOAD_SIZE};
+
+    #[test]
+    fn frames_are_placed_every_48_pixels() {
+        // a frame that is too wide, it must be clipped to 44 pixels
+        let frame = Styled::new(
+            Rectangle::new(Point::zero(), Size::new(60, 11)),
+            PrimitiveStyle::with_fill(BinaryColor::On),
+        );
+        let frames: FrameSequence<_> = [frame, frame].into_iter().collect();
+
+        let mut payload = PayloadBuffer::new();
+        payload.add_message_drawable(Style::default(), &frames);
+        let message = &payload.as_bytes()[64..];
+        assert_eq!(message.len(), 2 * FRAME_SIZE);
+
+        for (i, row) in message.chunks(11).enumerate() {
+            let expected = if i % 6 == 5 { 0xf0 } else { 0xff };
+            assert_eq!(row, [expected; 11], "byte column {i}");
+        }
+    }
+
+    #[test]
+    fn reduce_skips_frames_evenly() {
+        let mut frames: FrameSequence<_> = (0..10).collect();
+        frames.reduce(20);
+        assert_eq!(frames.len(), 10);
+        frames.reduce(4);
+        assert_eq!(frames.frames(), [0, 2, 5, 7]);
+    }
+
+    #[test]
+    fn max_frames_uses_remaining_payload() {
+        let mut payload = PayloadBuffer::new();
+        let max = max_frames(&payload);
+        assert_eq!(max, (MAX_PAYLOAD_SIZE - 64) / 66);
+
+        payload.add_message(Style::default(), 6);
+        assert_eq!(max_frames(&payload), max - 1);
+    }
+
+    #[test]
+    fn resample_frame_delays() {
+        let ms = Duration::from_millis;
+        assert_eq!(resample(&[], Speed::Fps15), Vec::<usize>::new());
+        // faster than the badge: frames are dropped
+        assert_eq!(resample(&[ms(33); 4], Speed::Fps15), [1, 3]);
+        // slower than the badge: frames are repeated
+        assert_eq!(resample(&[ms(200), ms(100)], Speed::Fps15), [0, 0, 0, 1, 1]);
+        // always at least one frame
+        assert_eq!(resample(&[ms(10)], Speed::Fps1_2), [0]);
+    }
+}
@@ -0,0 +1,136 @@
+//! Render payloads as images, e.g. to show them on a web page
+
+use std::time::Duration;
+
+use image::{Delay, Frame, Rgba, RgbaImage};
+
+use super::animation::{FRAME_HEIGHT, FRAME_STRIDE, FRAME_WIDTH};
+use crate::protocol::{Mode, PayloadBuffer, State};
+
+const LED_ON: Rgba<u8> = Rgba([255, 48, 32, 255]);
+const LED_OFF: Rgba<u8> = Rgba([48, 12, 8, 255]);
+const BACKGROUND: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+/// Draw LEDs, every LED is a square of `scale` pixels with a gap of one pixel
+fn draw_leds(width: usize, scale: u32, on: impl Fn(usize, usize) -> bool) -> RgbaImage {
+    let scale = scale.max(1);
+    #[allow(clippy::cast_possible_truncation)] // messages are at most a few thousand pixels wide
+    let mut image = RgbaImage::from_pixel(width as u32 * scale, FRAME_HEIGHT * scale, BACKGROUND);
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let gap = scale > 2 && (x % scale == scale - 1 || y % scale == scale - 1);
+        if !gap {
+            let led = on((x / scale) as usize, (y / scale) as usize);
+            *pixel = if led { LED_ON } else { LED_OFF };
+        }
+    }
+    image
+}
+
+/// Render every message of the payload below each other
+///
+/// Each column of a message is shown, independent of the display mode.
+/// ```
+/// use badgemagic::{
+///     protocol::{PayloadBuffer, Style},
+///     util::preview,
+/// };
+///
+/// let mut payload = PayloadBuffer::new();
+/// payload.add_message(Style::default(), 2);
+/// payload.add_message(Style::default(), 6);
+/// let image = preview::image(&payload, 4);
+/// assert_eq!((image.width(), image.height()), (48 * 4, (11 * 2 + 1) * 4));
+/// ```
+#[must_use]
+pub fn image(payload: &PayloadBuffer, scale: u32) -> RgbaImage {
+    let width = payload
+        .messages()
+        .map(|message| message.width())
+        .max()
+        .unwrap_or(FRAME_WIDTH as usize);
+    let count = u32::try_from(payload.num_messages()).unwrap_or_default();
+    let row_height = (FRAME_HEIGHT + 1) * scale;
+    #[allow(clippy::cast_possible_truncation)]
+    let mut image = RgbaImage::from_pixel(
+        width as u32 * scale,
+        (row_height * count).saturating_sub(scale),
+        BACKGROUND,
+    );
+    for (index, message) in payload.messages().enumerate() {
+        let leds = draw_leds(message.width(), scale, |x, y| {
+            message.get((x, y)) == Some(State::On)
+        });
+        #[allow(clippy::cast_possible_truncation)] // at most 8 messages
+        image::imageops::replace(&mut image, &leds, 0, i64::from(row_height * index as u32));
+    }
+    image
+}
+
+/// Simulate the display of the badge, showing every message once
+///
+/// The scroll modes ([`Mode::Left`] and [`Mode::Right`]) move the message one column per frame,
+/// [`Mode::Fast`] shows one screen every 48 columns. All other modes are shown as
+/// static screens of 44 columns, their transitions are not animated.
+///
+/// The frames are rendered while iterating, long messages result in thousands of frames.
+pub fn animation(payload: &PayloadBuffer, scale: u32) -> impl Iterator<Item = Frame> + '_ {
+    let display = FRAME_WIDTH as usize;
+    payload.messages().flat_map(move |message| {
+        let style = message.style();
+        let fps = style.speed().frames_per_second();
+        let delay = Delay::from_saturating_duration(Duration::from_secs_f32(1.0 / fps));
+
+        let width = message.width().cast_signed();
+        let display = display.cast_signed();
+        let (offsets, delay): (Vec<_>, _) = match style.mode() {
+            Mode::Left => ((-display..=width).collect(), delay),
+            Mode::Right => ((-display..=width).rev().collect(), delay),
+            Mode::Fast => ((0..width).step_by(FRAME_STRIDE as usize).collect(), delay),
+            Mode::Center if width <= display => (vec![(width - display) / 2], screen_delay()),
+            _ => (
+                (0..width).step_by(display.cast_unsigned()).collect(),
+                screen_delay(),
+            ),
+        };
+
+        offsets.into_iter().map(move |offset| {
+            let image = draw_leds(display.cast_unsigned(), scale, |x, y| {
+                usize::try_from(offset + x.cast_signed())
+                    .is_ok_and(|x| message.get((x, y)) == Some(State::On))
+            });
+            Frame::from_parts(image, 0, 0, delay)
+        })
+    })
+}
+
+/// Display duration of a static screen
+fn screen_delay() -> Delay {
+    Delay::from_saturating_duration(Duration::from_secs(2))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::protocol::{Mode, PayloadBuffer, State, Style};
+
+    use super::{animation, LED_OFF, LED_ON};
+
+    #[test]
+    fn animate_modes() {
+        let mut payload = PayloadBuffer::new();
+        payload
+            .add_message(Style::default().with_mode(Mode::Left), 2)
+            .set((0, 0), State::On);
+        payload.add_message(Style::default().with_mode(Mode::Center), 2);
+        payload.add_message(Style::default().with_mode(Mode::Fast), 12);
+        payload.add_message(Style::default().with_mode(Mode::Drop), 12);
+
+        let frames: Vec<_> = animation(&payload, 1).collect();
+        assert_eq!(frames.len(), (44 + 16 + 1) + 1 + 2 + 3);
+
+        // the first column of the scrolling message enters from the right
+        let first = frames[1].buffer();
+        assert_eq!(first.get_pixel(43, 0), &LED_ON);
+        assert_eq!(first.get_pixel(42, 0), &LED_OFF);
+        assert_eq!(frames[44].buffer().get_pixel(0, 0), &LED_ON);
+    }
+}
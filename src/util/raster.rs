@@ -0,0 +1,648 @@
+//! Convert raster images into monochrome images for the display
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Dimensions, Point, Size},
+    pixelcolor::BinaryColor,
+    primitives::Rectangle,
+    Drawable, Pixel,
+};
+use image::{imageops::FilterType, DynamicImage, GrayImage, Luma};
+
+/// Threshold used to decide whether a pixel is on or off
+///
+/// A pixel is switched on if its brightness is above the threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        rename_all = "snake_case",
+        expecting = "expected a brightness from 0 to 255 or \"otsu\""
+    )
+)]
+pub enum Threshold {
+    /// Calculate the threshold from the histogram of the image (Otsu's method)
+    Otsu,
+
+    /// Use a fixed brightness value (0 to 255)
+    #[cfg_attr(feature = "serde", serde(untagged))]
+    Fixed(u8),
+}
+
+/// Dithering algorithm used to simulate gray levels
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Dither {
+    /// Hard threshold, no dithering
+    #[default]
+    None,
+
+    /// Floyd–Steinberg error diffusion
+    FloydSteinberg,
+
+    /// Atkinson error diffusion, keeps more contrast than Floyd–Steinberg
+    Atkinson,
+
+    /// Ordered dithering with a 4x4 Bayer matrix
+    Ordered,
+}
+
+/// Filter used to resize images
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ResizeFilter {
+    /// Nearest neighbor, keeps hard edges of pixel art
+    #[default]
+    Nearest,
+
+    /// Linear filter
+    Triangle,
+
+    /// Cubic filter
+    CatmullRom,
+
+    /// Gaussian filter
+    Gaussian,
+
+    /// Lanczos filter with window 3, sharpest result for photos
+    Lanczos3,
+}
+
+impl From<ResizeFilter> for FilterType {
+    fn from(value: ResizeFilter) -> Self {
+        match value {
+            ResizeFilter::Nearest => Self::Nearest,
+            ResizeFilter::Triangle => Self::Triangle,
+            ResizeFilter::CatmullRom => Self::CatmullRom,
+            ResizeFilter::Gaussian => Self::Gaussian,
+            ResizeFilter::Lanczos3 => Self::Lanczos3,
+        }
+    }
+}
+
+/// How an image is scaled to fit the target area
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Fit {
+    /// Scale the image to fit into the area, keeping the aspect ratio
+    #[default]
+    Contain,
+
+    /// Scale the image to cover the whole area, keeping the aspect ratio.
+    /// The overflowing parts are cropped.
+    Cover,
+
+    /// Scale the image to the size of the area, ignoring the aspect ratio
+    Stretch,
+
+    /// Don't scale the image, crop it to the area
+    None,
+}
+
+/// Rectangle of the source image in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct Crop {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Placement of an image on the 11 pixel high display
+///
+/// The image is cropped, scaled into an area of `width` x 11 pixels,
+/// centered within that area, moved by the offset
+/// and finally padded with empty columns.
+/// ```
+/// # use badgemagic::protocol::{Mode, PayloadBuffer, Style};
+/// use badgemagic::util::raster::{Fit, ImageLayout, RasterOptions};
+///
+/// # let image = image::DynamicImage::new_luma8(100, 50);
+/// // center a logo on the 44 pixel wide screen
+/// let layout = ImageLayout {
+///     fit: Fit::Contain,
+///     width: Some(44),
+///     ..ImageLayout::default()
+/// };
+/// let mut buffer = PayloadBuffer::new();
+/// buffer.add_message_drawable(
+///     Style::default().with_mode(Mode::Center),
+///     &layout.render(&image, &RasterOptions::default()),
+/// );
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ImageLayout {
+    /// How the image is scaled into the area
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub fit: Fit,
+
+    /// Width of the area in pixels
+    ///
+    /// Defaults to the width of the scaled image for `contain` and `none`
+    /// and to the display width (44 pixels) for `cover` and `stretch`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub width: Option<u32>,
+
+    /// Only use this part of the source image
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub crop: Option<Crop>,
+
+    /// Move the image to the right (or left if negative) within the area
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub offset_x: i32,
+
+    /// Move the image down (or up if negative) within the area
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub offset_y: i32,
+
+    /// Empty columns in front of the area
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub padding_left: u32,
+
+    /// Empty columns after the area
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub padding_right: u32,
+}
+
+impl ImageLayout {
+    const HEIGHT: u32 = 11;
+
+    const DISPLAY_WIDTH: u32 = 44;
+
+    /// Place the `image` according to the layout and convert it into a monochrome image
+    #[must_use]
+    pub fn render(&self, image: &DynamicImage, options: &RasterOptions) -> BinaryImage {
+        let cropped;
+        let image = if let Some(crop) = self.crop {
+            cropped = image.crop_imm(crop.x, crop.y, crop.width, crop.height);
+            &cropped
+        } else {
+            image
+        };
+
+        let (width, height) = (image.width().max(1), image.height().max(1));
+        let filter = options.filter.into();
+        let scaled = match self.fit {
+            Fit::Contain => image.resize(self.width.unwrap_or(u32::MAX), Self::HEIGHT, filter),
+            Fit::Cover => {
+                let area_width = self.width.unwrap_or(Self::DISPLAY_WIDTH);
+                let scale = f64::max(
+                    f64::from(area_width) / f64::from(width),
+                    f64::from(Self::HEIGHT) / f64::from(height),
+                );
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                let size = |n: u32, min: u32| ((f64::from(n) * scale).round() as u32).max(min);
+                image.resize_exact(size(width, area_width), size(height, Self::HEIGHT), filter)
+            }
+            Fit::Stretch => image.resize_exact(
+                self.width.unwrap_or(Self::DISPLAY_WIDTH),
+                Self::HEIGHT,
+                filter,
+            ),
+            Fit::None => image.clone(),
+        };
+        let scaled = options.binarize(&scaled);
+
+        let area_width = self.width.unwrap_or(scaled.width);
+        let left = (i64::from(area_width) - i64::from(scaled.width)) / 2 + i64::from(self.offset_x);
+        let top =
+            (i64::from(Self::HEIGHT) - i64::from(scaled.height)) / 2 + i64::from(self.offset_y);
+
+        let mut result = BinaryImage::new(
+            self.padding_left + area_width + self.padding_right,
+            Self::HEIGHT,
+        );
+        for y in 0..Self::HEIGHT {
+            for x in 0..area_width {
+                let source = (
+                    u32::try_from(i64::from(x) - left),
+                    u32::try_from(i64::from(y) - top),
+                );
+                if let (Ok(source_x), Ok(source_y)) = source {
+                    if scaled.get(source_x, source_y) == Some(true) {
+                        result.set(self.padding_left + x, y, true);
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Options to convert a raster image into a monochrome image
+/// ```
+/// use badgemagic::util::raster::{Dither, RasterOptions, ResizeFilter};
+///
+/// let options = RasterOptions {
+///     dither: Dither::Atkinson,
+///     filter: ResizeFilter::Lanczos3,
+///     ..RasterOptions::default()
+/// };
+/// # let _ = options;
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RasterOptions {
+    /// Brightness threshold
+    ///
+    /// Defaults to `31` without dithering and to `127` with dithering.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub threshold: Option<Threshold>,
+
+    /// Dithering algorithm
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub dither: Dither,
+
+    /// Invert the image before converting it (e.g. for dark logos on a white background)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub invert: bool,
+
+    /// Filter used when the image is resized
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub filter: ResizeFilter,
+}
+
+impl RasterOptions {
+    /// Resize the image to a height of 11 pixels (keeping the aspect ratio)
+    /// and convert it into a monochrome image
+    ///
+    /// Use [`ImageLayout`] to control the placement of the image.
+    #[must_use]
+    pub fn convert(&self, image: &DynamicImage) -> BinaryImage {
+        ImageLayout::default().render(image, self)
+    }
+
+    /// Convert the image into a monochrome image without resizing it
+    ///
+    /// Transparent pixels are treated as black, also if the image is inverted.
+    #[must_use]
+    pub fn binarize(&self, image: &DynamicImage) -> BinaryImage {
+        let image = image.to_luma_alpha8();
+        let gray = GrayImage::from_fn(image.width(), image.height(), |x, y| {
+            let [mut luma, alpha] = image.get_pixel(x, y).0;
+            if self.invert {
+                luma = 255 - luma;
+            }
+            #[allow(clippy::cast_possible_truncation)] // the result is at most 255
+            Luma([(u16::from(luma) * u16::from(alpha) / 255) as u8])
+        });
+
+        let threshold = match self.threshold {
+            Some(Threshold::Fixed(threshold)) => threshold,
+            Some(Threshold::Otsu) => otsu_threshold(&gray),
+            None if self.dither == Dither::None => 31,
+            None => 127,
+        };
+
+        let pixels = match self.dither {
+            Dither::None => gray.pixels().map(|p| p.0[0] > threshold).collect(),
+            Dither::FloydSteinberg => diffuse_error(
+                &gray,
+                threshold,
+                16,
+                &[(1, 0, 7), (-1, 1, 3), (0, 1, 5), (1, 1, 1)],
+            ),
+            Dither::Atkinson => diffuse_error(
+                &gray,
+                threshold,
+                8,
+                &[
+                    (1, 0, 1),
+                    (2, 0, 1),
+                    (-1, 1, 1),
+                    (0, 1, 1),
+                    (1, 1, 1),
+                    (0, 2, 1),
+                ],
+            ),
+            Dither::Ordered => ordered(&gray, threshold),
+        };
+
+        BinaryImage {
+            width: gray.width(),
+            height: gray.height(),
+            pixels,
+        }
+    }
+}
+
+/// Calculate the threshold that minimizes the intra-class variance
+fn otsu_threshold(image: &GrayImage) -> u8 {
+    let mut histogram = [0u64; 256];
+    for pixel in image.pixels() {
+        histogram[usize::from(pixel.0[0])] += 1;
+    }
+
+    let total: u64 = histogram.iter().sum();
+    let sum: u64 = (0..).zip(histogram).map(|(i, n)| i * n).sum();
+
+    let mut best = (0, 0.0);
+    let (mut dark_count, mut dark_sum) = (0, 0);
+    for (threshold, n) in (0..=u8::MAX).zip(histogram) {
+        dark_count += n;
+        dark_sum += u64::from(threshold) * n;
+        let light_count = total - dark_count;
+        if dark_count == 0 || light_count == 0 {
+            continue;
+        }
+
+        #[allow(clippy::cast_precision_loss)] // pixel counts are far below 2^52
+        let variance = {
+            let dark_mean = dark_sum as f64 / dark_count as f64;
+            let light_mean = (sum - dark_sum) as f64 / light_count as f64;
+            dark_count as f64 * light_count as f64 * (dark_mean - light_mean).powi(2)
+        };
+        if variance > best.1 {
+            best = (threshold, variance);
+        }
+    }
+    best.0
+}
+
+/// Error diffusion dithering with the given `(dx, dy, weight)` kernel
+fn diffuse_error(
+    image: &GrayImage,
+    threshold: u8,
+    divisor: i32,
+    kernel: &[(i64, i64, i32)],
+) -> Vec<bool> {
+    let (width, height) = (i64::from(image.width()), i64::from(image.height()));
+    let index = |x: i64, y: i64| usize::try_from(y * width + x).unwrap();
+
+    let mut values: Vec<i32> = image.pixels().map(|p| i32::from(p.0[0])).collect();
+    let mut pixels = vec![false; values.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let value = values[index(x, y)];
+            let on = value > i32::from(threshold);
+            pixels[index(x, y)] = on;
+
+            let error = value - if on { 255 } else { 0 };
+            for &(dx, dy, weight) in kernel {
+                let (x, y) = (x + dx, y + dy);
+                if (0..width).contains(&x) && (0..height).contains(&y) {
+                    values[index(x, y)] += error * weight / divisor;
+                }
+            }
+        }
+    }
+    pixels
+}
+
+/// Ordered dithering with a 4x4 Bayer matrix centered around `threshold`
+fn ordered(image: &GrayImage, threshold: u8) -> Vec<bool> {
+    const BAYER: [[i32; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+    image
+        .enumerate_pixels()
+        .map(|(x, y, pixel)| {
+            let bias = BAYER[y as usize % 4][x as usize % 4] * 16 + 8 - 128;
+            i32::from(pixel.0[0]) + bias > i32::from(threshold)
+        })
+        .collect()
+}
+
+/// A monochrome image
+///
+/// Only the pixels that are on are drawn, so it can be layered on top of other content.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BinaryImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<bool>,
+}
+
+impl BinaryImage {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![false; (width * height) as usize],
+        }
+    }
+
+    fn set(&mut self, x: u32, y: u32, on: bool) {
+        self.pixels[(y * self.width + x) as usize] = on;
+    }
+
+    /// Width of the image in pixels
+    #[must_use]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height of the image in pixels
+    #[must_use]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Return whether the pixel at (`x`, `y`) is on
+    ///
+    /// Returns `None` if the pixel is out of bounds.
+    #[must_use]
+    pub fn get(&self, x: u32, y: u32) -> Option<bool> {
+        if x < self.width && y < self.height {
+            Some(self.pixels[(y * self.width + x) as usize])
+        } else {
+            None
+        }
+    }
+}
+
+impl Dimensions for BinaryImage {
+    fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(Point::zero(), Size::new(self.width, self.height))
+    }
+}
+
+impl Drawable for BinaryImage {
+    type Color = BinaryColor;
+
+    type Output = ();
+
+    fn draw<D>(&self, target: &mut D) -> Result<Self::Output, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        let width = self.width.max(1) as usize;
+        target.draw_iter(
+            (0..)
+                .zip(&self.pixels)
+                .filter(|(_, &on)| on)
+                .map(|(i, _): (usize, _)| {
+                    let x = (i % width).try_into().unwrap_or(i32::MAX);
+                    let y = (i / width).try_into().unwrap_or(i32::MAX);
+                    Pixel(Point::new(x, y), BinaryColor::On)
+                }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use image::{DynamicImage, GrayAlphaImage, GrayImage, Luma, LumaA};
+
+    use super::{BinaryImage, Crop, Dither, Fit, ImageLayout, RasterOptions, Threshold};
+
+    fn gradient() -> DynamicImage {
+        #[allow(clippy::cast_possible_truncation)]
+        DynamicImage::ImageLuma8(GrayImage::from_fn(64, 11, |x, _| Luma([(x * 4) as u8])))
+    }
+
+    fn count_on(options: RasterOptions) -> usize {
+        let image = options.binarize(&gradient());
+        (0..image.height())
+            .flat_map(|y| (0..image.width()).map(move |x| (x, y)))
+            .filter(|&(x, y)| image.get(x, y).unwrap())
+            .count()
+    }
+
+    #[test]
+    fn default_threshold_matches_previous_behaviour() {
+        // luma > 31 is switched on: x >= 8
+        assert_eq!(count_on(RasterOptions::default()), 56 * 11);
+        let inverted = RasterOptions {
+            invert: true,
+            ..RasterOptions::default()
+        };
+        assert_eq!(count_on(inverted), 56 * 11);
+    }
+
+    #[test]
+    fn transparent_pixels_stay_off() {
+        // dark logo on a transparent background
+        let image = DynamicImage::ImageLumaA8(GrayAlphaImage::from_fn(8, 11, |x, _| {
+            if x < 2 {
+                LumaA([0, 255])
+            } else {
+                LumaA([0, 0])
+            }
+        }));
+        let on = |options: &RasterOptions| {
+            let image = options.binarize(&image);
+            (0..8).filter(|&x| image.get(x, 0).unwrap()).count()
+        };
+        assert_eq!(on(&RasterOptions::default()), 0);
+        let inverted = RasterOptions {
+            invert: true,
+            ..RasterOptions::default()
+        };
+        assert_eq!(on(&inverted), 2);
+    }
+
+    #[test]
+    fn fixed_and_otsu_threshold() {
+        let fixed = RasterOptions {
+            threshold: Some(Threshold::Fixed(127)),
+            ..RasterOptions::default()
+        };
+        assert_eq!(count_on(fixed), 32 * 11);
+
+        let otsu = RasterOptions {
+            threshold: Some(Threshold::Otsu),
+            ..RasterOptions::default()
+        };
+        assert!((28 * 11..=36 * 11).contains(&count_on(otsu)));
+    }
+
+    #[test]
+    fn dithering_preserves_average_brightness() {
+        for dither in [Dither::FloydSteinberg, Dither::Atkinson, Dither::Ordered] {
+            let on = count_on(RasterOptions {
+                dither,
+                ..RasterOptions::default()
+            });
+            // the gradient has an average brightness of ~50%
+            assert!((250..=450).contains(&on), "{dither:?}: {on}");
+        }
+    }
+
+    fn white(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageLuma8(GrayImage::from_pixel(width, height, Luma([255])))
+    }
+
+    /// Return the bounding box of all pixels that are on: `(left, top, right, bottom)`
+    fn lit_area(image: &BinaryImage) -> (u32, u32, u32, u32) {
+        let lit: Vec<_> = (0..image.height())
+            .flat_map(|y| (0..image.width()).map(move |x| (x, y)))
+            .filter(|&(x, y)| image.get(x, y).unwrap())
+            .collect();
+        (
+            lit.iter().map(|p| p.0).min().unwrap(),
+            lit.iter().map(|p| p.1).min().unwrap(),
+            lit.iter().map(|p| p.0).max().unwrap(),
+            lit.iter().map(|p| p.1).max().unwrap(),
+        )
+    }
+
+    fn render(layout: ImageLayout, image: &DynamicImage) -> BinaryImage {
+        layout.render(image, &RasterOptions::default())
+    }
+
+    #[test]
+    fn layout_default_keeps_aspect_ratio() {
+        let image = render(ImageLayout::default(), &white(40, 20));
+        assert_eq!((image.width(), image.height()), (22, 11));
+        assert_eq!(lit_area(&image), (0, 0, 21, 10));
+    }
+
+    #[test]
+    fn layout_fit_modes() {
+        let layout = |fit| ImageLayout {
+            fit,
+            width: Some(44),
+            ..ImageLayout::default()
+        };
+        let contain = render(layout(Fit::Contain), &white(40, 20));
+        assert_eq!(contain.width(), 44);
+        assert_eq!(lit_area(&contain), (11, 0, 32, 10));
+
+        let cover = render(layout(Fit::Cover), &white(10, 20));
+        assert_eq!(lit_area(&cover), (0, 0, 43, 10));
+
+        let stretch = render(layout(Fit::Stretch), &white(10, 20));
+        assert_eq!(lit_area(&stretch), (0, 0, 43, 10));
+
+        let none = render(layout(Fit::None), &white(10, 5));
+        assert_eq!(lit_area(&none), (17, 3, 26, 7));
+    }
+
+    #[test]
+    fn layout_crop_offset_and_padding() {
+        let mut image = GrayImage::new(20, 11);
+        for y in 0..11 {
+            image.put_pixel(15, y, Luma([255]));
+        }
+        let layout = ImageLayout {
+            fit: Fit::None,
+            crop: Some(Crop {
+                x: 10,
+                y: 0,
+                width: 10,
+                height: 11,
+            }),
+            offset_x: -2,
+            padding_left: 3,
+            padding_right: 4,
+            ..ImageLayout::default()
+        };
+        let result = render(layout, &DynamicImage::ImageLuma8(image));
+        assert_eq!(result.width(), 3 + 10 + 4);
+        assert_eq!(lit_area(&result), (3 + 5 - 2, 0, 3 + 5 - 2, 10));
+    }
+}
@@ -0,0 +1,107 @@
+//! Render SVG images for the display
+
+use std::sync::{Arc, OnceLock};
+
+use anyhow::{Context, Result};
+use image::{DynamicImage, RgbaImage};
+use resvg::{
+    tiny_skia::{Pixmap, Transform},
+    usvg::{fontdb::Database, Options, Tree},
+};
+
+use super::raster::{BinaryImage, RasterOptions};
+
+/// Rasterize an SVG document directly at the display height of 11 pixels
+/// and convert it into a monochrome image
+///
+/// The aspect ratio is kept, so vector logos stay sharp instead of being scaled down
+/// from a larger raster image. Text in the SVG is rendered with the system fonts.
+/// ```
+/// # use badgemagic::protocol::{PayloadBuffer, Style};
+/// use badgemagic::util::{raster::RasterOptions, svg};
+///
+/// let logo = br#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10">
+///     <circle cx="5" cy="5" r="5" fill="white"/>
+/// </svg>"#;
+/// let image = svg::render(logo, &RasterOptions::default()).unwrap();
+/// assert_eq!((image.width(), image.height()), (22, 11));
+///
+/// let mut buffer = PayloadBuffer::new();
+/// buffer.add_message_drawable(Style::default(), &image);
+/// ```
+pub fn render(data: &[u8], options: &RasterOptions) -> Result<BinaryImage> {
+    const HEIGHT: f32 = 11.0;
+
+    let svg_options = Options {
+        fontdb: system_fonts(),
+        ..Options::default()
+    };
+    let tree = Tree::from_data(data, &svg_options).context("parse svg")?;
+
+    let size = tree.size();
+    let scale = HEIGHT / size.height();
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let width = (size.width() * scale).round().max(1.0) as u32;
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let mut pixmap = Pixmap::new(width, HEIGHT as u32).context("svg is too large")?;
+    resvg::render(
+        &tree,
+        Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    let pixels = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    let image = RgbaImage::from_raw(pixmap.width(), pixmap.height(), pixels)
+        .context("convert svg image")?;
+    Ok(options.binarize(&DynamicImage::ImageRgba8(image)))
+}
+
+/// Return the system fonts, they are only loaded once because loading them is slow
+fn system_fonts() -> Arc<Database> {
+    static FONTS: OnceLock<Arc<Database>> = OnceLock::new();
+    FONTS
+        .get_or_init(|| {
+            let mut fonts = Database::new();
+            fonts.load_system_fonts();
+            Arc::new(fonts)
+        })
+        .clone()
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::{render, system_fonts};
+    use crate::util::raster::RasterOptions;
+
+    #[test]
+    fn svg_is_scaled_to_display_height() {
+        let svg = |width: u32, height: u32| {
+            format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {width} {height}">
+                    <rect width="{width}" height="{height}" fill="white"/>
+                </svg>"#
+            )
+        };
+        for (width, height, expected) in [(100, 50, 22), (44, 11, 44), (440, 1100, 4), (1, 100, 1)]
+        {
+            let image = render(svg(width, height).as_bytes(), &RasterOptions::default()).unwrap();
+            assert_eq!(
+                (image.width(), image.height()),
+                (expected, 11),
+                "{width}x{height}"
+            );
+        }
+
+        assert!(render(b"<svg", &RasterOptions::default()).is_err());
+        assert!(Arc::ptr_eq(&system_fonts(), &system_fonts()));
+    }
+}
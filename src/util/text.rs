@@ -0,0 +1,373 @@
+//! Text layouts for the 44x11 pixel display
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Dimensions, Point, Size},
+    pixelcolor::BinaryColor,
+    primitives::Rectangle,
+    text::Text,
+    Drawable,
+};
+use u8g2_fonts::{
+    fonts::{
+        u8g2_font_4x6_tf, u8g2_font_5x7_tf, u8g2_font_6x10_tf, u8g2_font_7x13_tf,
+        u8g2_font_haxrcorp4089_tr, u8g2_font_lucasfont_alternate_tf, u8g2_font_squeezed_r7_tr,
+        u8g2_font_tom_thumb_4x6_tf,
+    },
+    types::VerticalPosition,
+    Font, FontRenderer, U8g2TextStyle,
+};
+
+const DISPLAY_WIDTH: u32 = 44;
+const DISPLAY_HEIGHT: i32 = 11;
+
+/// Horizontal alignment of a line of text
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Alignment {
+    /// Align the line with the left edge of the message
+    #[default]
+    Left,
+
+    /// Center the line within the message
+    Center,
+
+    /// Align the line with the right edge of the message
+    Right,
+}
+
+/// Two lines of text rendered with a tiny 5 pixel font
+///
+/// The message is as wide as the longer line,
+/// the shorter line is aligned within that width.
+/// ```
+/// # use badgemagic::protocol::{PayloadBuffer, Style};
+/// use badgemagic::util::text::{Alignment, TwoLines};
+///
+/// let mut buffer = PayloadBuffer::new();
+/// buffer.add_message_drawable(
+///     Style::default(),
+///     &TwoLines::new("Jane Doe", "FOSSASIA").align(Alignment::Center, Alignment::Center),
+/// );
+/// ```
+#[must_use]
+pub struct TwoLines<'a> {
+    lines: [(Text<'a, U8g2TextStyle<BinaryColor>>, Alignment); 2],
+}
+
+impl<'a> TwoLines<'a> {
+    /// Baselines of the top and bottom line
+    const BASELINES: [i32; 2] = [4, 10];
+
+    /// Create a new two line layout, both lines are left aligned
+    pub fn new(top: &'a str, bottom: &'a str) -> Self {
+        let line = |text, y| {
+            (
+                Text::new(
+                    text,
+                    Point::new(0, y),
+                    U8g2TextStyle::new(u8g2_font_tom_thumb_4x6_tf, BinaryColor::On),
+                ),
+                Alignment::default(),
+            )
+        };
+        Self {
+            lines: [
+                line(top, Self::BASELINES[0]),
+                line(bottom, Self::BASELINES[1]),
+            ],
+        }
+    }
+
+    /// Set the horizontal alignment of the top and bottom line
+    pub fn align(mut self, top: Alignment, bottom: Alignment) -> Self {
+        self.lines[0].1 = top;
+        self.lines[1].1 = bottom;
+        self
+    }
+
+    fn line_width(text: &Text<'_, U8g2TextStyle<BinaryColor>>) -> u32 {
+        let bounds = text.bounding_box();
+        (bounds.top_left.x + i32::try_from(bounds.size.width).unwrap_or(i32::MAX))
+            .try_into()
+            .unwrap_or_default()
+    }
+
+    fn width(&self) -> u32 {
+        self.lines
+            .iter()
+            .map(|(text, _)| Self::line_width(text))
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+impl Dimensions for TwoLines<'_> {
+    fn bounding_box(&self) -> Rectangle {
+        let width = self.width();
+        let height = if width == 0 { 0 } else { 11 };
+        Rectangle::new(Point::zero(), Size::new(width, height))
+    }
+}
+
+impl Drawable for TwoLines<'_> {
+    type Color = BinaryColor;
+
+    type Output = ();
+
+    fn draw<D>(&self, target: &mut D) -> Result<Self::Output, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        let width = self.width();
+        for (text, alignment) in &self.lines {
+            let free = width - Self::line_width(text);
+            let offset = match alignment {
+                Alignment::Left => 0,
+                Alignment::Center => free / 2,
+                Alignment::Right => free,
+            };
+            let mut text = text.clone();
+            text.position.x = offset.try_into().unwrap_or(i32::MAX);
+            text.draw(target)?;
+        }
+        Ok(())
+    }
+}
+
+/// Text rendered with the largest font that fits the display without scrolling
+///
+/// The fonts are tried from the largest to the smallest.
+/// Optionally the text can be wrapped into [`TwoLines`] if it does not fit on a single line.
+/// ```
+/// # use badgemagic::protocol::{Mode, PayloadBuffer, Style};
+/// use badgemagic::util::text::FittedText;
+///
+/// let mut buffer = PayloadBuffer::new();
+/// if let Some(text) = FittedText::new("Hello World", true) {
+///     buffer.add_message_drawable(Style::default().with_mode(Mode::Center), &text);
+/// }
+/// ```
+#[must_use]
+pub enum FittedText<'a> {
+    /// A single line of text
+    OneLine(Text<'a, U8g2TextStyle<BinaryColor>>),
+
+    /// The text wrapped into two lines with a tiny font
+    TwoLines(TwoLines<'a>),
+}
+
+impl<'a> FittedText<'a> {
+    /// Fit `text` into the 44x11 pixel display
+    ///
+    /// If `wrap` is set, the text is split into two lines at a whitespace
+    /// when no font is small enough to show it on a single line.
+    ///
+    /// Returns `None` if the text does not fit, even with the smallest font.
+    pub fn new(text: &'a str, wrap: bool) -> Option<Self> {
+        let one_line = Self::fonts()
+            .into_iter()
+            .find_map(|(style, renderer)| Self::line(text, style, &renderer))
+            .map(Self::OneLine);
+        if one_line.is_some() || !wrap {
+            return one_line;
+        }
+
+        text.char_indices()
+            .filter(|(_, c)| c.is_whitespace())
+            .map(|(i, c)| {
+                TwoLines::new(text[..i].trim_end(), text[i + c.len_utf8()..].trim_start())
+                    .align(Alignment::Center, Alignment::Center)
+            })
+            .filter(|lines| lines.width() <= DISPLAY_WIDTH)
+            .min_by_key(TwoLines::width)
+            .map(Self::TwoLines)
+    }
+
+    /// Place `text` on a single line, centered vertically
+    ///
+    /// Returns `None` if the font lacks a glyph or the drawn pixels don't fit the display.
+    fn line(
+        text: &'a str,
+        style: U8g2TextStyle<BinaryColor>,
+        renderer: &FontRenderer,
+    ) -> Option<Text<'a, U8g2TextStyle<BinaryColor>>> {
+        let dimensions = renderer
+            .get_rendered_dimensions(text, Point::zero(), VerticalPosition::Baseline)
+            .ok()?;
+        // center the drawn pixels, accents and descenders are not covered by the font ascent
+        let baseline = match dimensions.bounding_box {
+            Some(bounds) => {
+                let height = i32::try_from(bounds.size.height).ok()?;
+                if height > DISPLAY_HEIGHT || bounds.top_left.x < 0 {
+                    return None;
+                }
+                // `U8g2TextStyle` draws the alphabetic baseline one row below the renderer
+                (DISPLAY_HEIGHT - height) / 2 - bounds.top_left.y - 1
+            }
+            None => DISPLAY_HEIGHT - 2,
+        };
+        let text = Text::new(text, Point::new(0, baseline), style);
+        (TwoLines::line_width(&text) <= DISPLAY_WIDTH).then_some(text)
+    }
+
+    /// Fonts ordered from the largest to the smallest
+    fn fonts() -> [(U8g2TextStyle<BinaryColor>, FontRenderer); 8] {
+        fn font<F: Font>(font: F) -> (U8g2TextStyle<BinaryColor>, FontRenderer) {
+            (
+                U8g2TextStyle::new(font, BinaryColor::On),
+                FontRenderer::new::<F>(),
+            )
+        }
+
+        [
+            font(u8g2_font_7x13_tf),
+            font(u8g2_font_6x10_tf),
+            font(u8g2_font_lucasfont_alternate_tf),
+            font(u8g2_font_haxrcorp4089_tr),
+            font(u8g2_font_5x7_tf),
+            font(u8g2_font_squeezed_r7_tr),
+            font(u8g2_font_4x6_tf),
+            font(u8g2_font_tom_thumb_4x6_tf),
+        ]
+    }
+}
+
+impl Dimensions for FittedText<'_> {
+    fn bounding_box(&self) -> Rectangle {
+        match self {
+            Self::OneLine(text) => text.bounding_box(),
+            Self::TwoLines(lines) => lines.bounding_box(),
+        }
+    }
+}
+
+impl Drawable for FittedText<'_> {
+    type Color = BinaryColor;
+
+    type Output = ();
+
+    fn draw<D>(&self, target: &mut D) -> Result<Self::Output, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        match self {
+            Self::OneLine(text) => text.draw(target).map(|_| ()),
+            Self::TwoLines(lines) => lines.draw(target),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use embedded_graphics::{
+        geometry::{Dimensions, Point},
+        mock_display::MockDisplay,
+        pixelcolor::BinaryColor,
+        Drawable,
+    };
+
+    use super::{Alignment, FittedText, TwoLines};
+
+    fn draw(text: &impl Drawable<Color = BinaryColor>) -> MockDisplay<BinaryColor> {
+        let mut display = MockDisplay::new();
+        display.set_allow_overdraw(true);
+        text.draw(&mut display).unwrap();
+        display
+    }
+
+    fn on_pixels(display: &MockDisplay<BinaryColor>) -> Vec<Point> {
+        (0..64)
+            .flat_map(|y| (0..64).map(move |x| Point::new(x, y)))
+            .filter(|&p| display.get_pixel(p) == Some(BinaryColor::On))
+            .collect()
+    }
+
+    #[test]
+    fn two_lines_fit_display_height() {
+        let lines = TwoLines::new("Tygq", "ABC");
+        assert_eq!(lines.bounding_box().size.height, 11);
+
+        let display = draw(&lines);
+        let rows: Vec<_> = (0..64)
+            .filter(|&y| {
+                (0..64).any(|x| display.get_pixel(Point::new(x, y)) == Some(BinaryColor::On))
+            })
+            .collect();
+        assert_eq!(rows.first(), Some(&0));
+        assert_eq!(rows.last(), Some(&10));
+    }
+
+    #[test]
+    fn shorter_line_is_aligned() {
+        for (alignment, expected) in [
+            (Alignment::Left, 0..3),
+            (Alignment::Center, 16..19),
+            (Alignment::Right, 32..35),
+        ] {
+            let lines = TwoLines::new("long long", "I").align(Alignment::Left, alignment);
+            assert_eq!(lines.bounding_box().size.width, 35);
+
+            let display = draw(&lines);
+            let bottom_line: Vec<_> = (0..35)
+                .filter(|&x| display.get_pixel(Point::new(x, 8)) == Some(BinaryColor::On))
+                .collect();
+            assert!(!bottom_line.is_empty());
+            assert!(
+                bottom_line.iter().all(|x| expected.contains(x)),
+                "{alignment:?}: {bottom_line:?}",
+            );
+        }
+    }
+
+    #[test]
+    fn fitted_text_prefers_large_fonts() {
+        let Some(FittedText::OneLine(short)) = FittedText::new("Hi", false) else {
+            panic!("short text should fit on one line");
+        };
+        let Some(FittedText::OneLine(long)) = FittedText::new("Hello World", false) else {
+            panic!("long text should fit on one line");
+        };
+        assert!(short.bounding_box().size.height > long.bounding_box().size.height);
+
+        for text in [&short, &long] {
+            let display = draw(text);
+            let drawn = on_pixels(&display);
+            assert!(drawn
+                .iter()
+                .all(|p| (0..44).contains(&p.x) && (0..11).contains(&p.y)));
+        }
+    }
+
+    #[test]
+    fn fitted_text_keeps_accents_and_descenders() {
+        for text in ["Äg", "jÉ", "ÅÖ", "Hi", "gyp"] {
+            let Some(fitted) = FittedText::new(text, false) else {
+                panic!("{text:?} should fit on one line");
+            };
+            let drawn = on_pixels(&draw(&fitted));
+            let rows: Vec<_> = drawn.iter().map(|p| p.y).collect();
+            let (top, bottom) = (*rows.iter().min().unwrap(), *rows.iter().max().unwrap());
+            // the mock display panics for negative coordinates, so rows above 0 are caught too
+            assert!(bottom < 11, "{text:?} is drawn at rows {top}..={bottom}");
+            assert!(
+                (top - (10 - bottom)).abs() <= 1,
+                "{text:?} is not centered: rows {top}..={bottom}"
+            );
+        }
+    }
+
+    #[test]
+    fn fitted_text_wraps_into_two_lines() {
+        let text = "Open Source Summit";
+        assert!(FittedText::new(text, false).is_none());
+        let Some(FittedText::TwoLines(lines)) = FittedText::new(text, true) else {
+            panic!("text should fit on two lines");
+        };
+        assert!(lines.bounding_box().size.width <= 44);
+
+        assert!(FittedText::new("Supercalifragilisticexpialidocious", true).is_none());
+    }
+}